@@ -7,6 +7,7 @@ use ::syn::{
     DeriveInput,
     parse::{Parse, ParseStream},
     parse_macro_input,
+    spanned::Spanned,
 };
 
 /// example: `#[any_sub_trait(T1, T2, ...)]`
@@ -30,15 +31,45 @@ impl Parse for SubTraits {
 /// ```ignore
 /// #[derive(AnySubTrait)]
 /// #[any_sub_trait(TraitA, TraitB, ...)] // optional
-/// struct MyStruct {}
+/// struct MyStruct {
+///     #[any_children] // optional, see `AnyTraitCast::cast_all`
+///     widgets: Vec<Box<dyn AnyTrait>>,
+/// }
 /// ```
-#[proc_macro_derive(AnySubTrait, attributes(any_sub_trait))]
+///
+/// `dyn core::any::Any` is always listed after your traits
+/// (`any_trait::compose` lists it at index 2 instead), unless you already
+/// listed `Any` yourself.\
+/// Add `#[any_send_sync]` to list `dyn Any + Send + Sync` and
+/// `dyn TraitA + Send + Sync`... too (the struct must be `Send + Sync`).\
+/// This is opt-in because the derive only sees the tokens of the struct:
+/// it can't know if it is `Send + Sync`, and the `const` list of types can't
+/// depend on whether a trait is implemented. Listing them always would
+/// not compile for types that are not `Send + Sync`.\
+/// Without it, `any_trait::AsAnyTraitSend` and `AsAnyTraitSync` still work,
+/// but only `cast_ref` to the plain `dyn TraitA` succeeds.
+///
+/// Add `#[any_vtables]` to recover the type from the vtable of any of its
+/// traits, see `any_trait::vtable`
+///
+/// Generic structs are supported, all type parameters must be `'static`
+/// and `Sized`: with a `T: ?Sized` parameter some instances would be sized
+/// and some not, and they can't share one list of types.
+///
+/// Unsized structs (ending with a slice or a `str`) are supported too, but
+/// they can only be cast to themselves: rust can't make a `dyn Trait` out
+/// of them, so they can't list any trait. The rows of `dyn AnyTrait` and
+/// `dyn Any` are the concrete type again.
+#[proc_macro_derive(
+    AnySubTrait,
+    attributes(any_sub_trait, any_children, any_send_sync, any_vtables)
+)]
+#[allow(clippy::iter_nth_zero)]
 pub fn derive_anytrait(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     // make sure we are only called on structs
-    let ::syn::Data::Struct(_items) = &input.data else {
-        use ::syn::spanned::Spanned;
+    let ::syn::Data::Struct(items) = &input.data else {
         return ::syn::Error::new(
             //
             input.span(),
@@ -69,126 +100,306 @@ pub fn derive_anytrait(input: TokenStream) -> TokenStream {
         .unwrap()
         .0;
 
-    let extra_traits_num: ::syn::Index = ::syn::Index::from(extra_traits.len());
-    let tot_traits: ::syn::Index = ::syn::Index::from(2 + extra_traits.len());
+    let send_sync = input
+        .attrs
+        .iter()
+        .find(|a| a.path().is_ident("any_send_sync"));
+    let vtables = input
+        .attrs
+        .iter()
+        .find(|a| a.path().is_ident("any_vtables"));
+    if let Some(attr) = vtables
+        && !input.generics.params.is_empty()
+    {
+        return ::syn::Error::new(
+            attr.span(),
+            "#[derive(AnyTrait)]: `any_vtables` needs a non-generic struct",
+        )
+        .to_compile_error()
+        .into();
+    }
 
-    let mut trait_idx_name =
-        Vec::<::syn::Ident>::with_capacity(extra_traits.len());
-    extra_traits.iter().enumerate().for_each(|(idx, _a)| {
-        trait_idx_name.push(::quote::format_ident!("N_{}", idx + 2));
+    // a `T: ?Sized` parameter would make the rows depend on `T`: `Tail<u8>`
+    // can list `dyn AnyTrait`, `Tail<[u8]>` can't, but we have one impl
+    let maybe_sized = |b: &::syn::TypeParamBound| {
+        matches!(b, ::syn::TypeParamBound::Trait(t)
+            if matches!(t.modifier, ::syn::TraitBoundModifier::Maybe(_)))
+    };
+    let param_unsized = input
+        .generics
+        .type_params()
+        .find(|p| p.bounds.iter().any(maybe_sized))
+        .map(|p| p.span());
+    let where_unsized = input.generics.where_clause.iter().find_map(|w| {
+        w.predicates.iter().find_map(|p| match p {
+            ::syn::WherePredicate::Type(t)
+                if t.bounds.iter().any(maybe_sized) =>
+            {
+                Some(t.span())
+            }
+            _ => None,
+        })
     });
+    if let Some(span) = param_unsized.or(where_unsized) {
+        return ::syn::Error::new(
+            span,
+            "#[derive(AnyTrait)]: `?Sized` type parameters are not supported",
+        )
+        .to_compile_error()
+        .into();
+    }
 
-    let out = quote! {
-        impl AnyTrait for #name
-          where #name: #(#extra_traits)+*
-        {
-            fn type_ids(&self) -> &'static [::any_trait::typeidconst::TypeIdConst] {
-                const TRAITS : [::any_trait::typeidconst::TypeIdConst; #tot_traits] =
-                    ::any_trait::typeidconst::append_array::
-                        <#name, #extra_traits_num, #tot_traits>(
-                    /* waiting for const Ord on TypeId...
-                    ::any_trait::typeidconst::sort_array(
-                        [#(::any_trait::typeidconst::TypeIdConst::of::
-                            <dyn #extra_traits>()),*])
-                    */
-                    [#(::any_trait::typeidconst::TypeIdConst::of::
-                        <dyn #extra_traits>()),*]
-                        );
-                &TRAITS
+    // `AnyTrait: 'static`, so all type parameters must be too
+    let mut generics = input.generics.clone();
+    generics
+        .type_params_mut()
+        .for_each(|p| p.bounds.push(::syn::parse_quote! { 'static }));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| ::syn::parse_quote! { where });
+
+    // one row per type after `dyn AnyTrait` and `Self`: the user traits,
+    // then `dyn Any` unless the user listed it, then maybe all of them
+    // `+ Send + Sync`. As types: `dyn A + B` must be in parentheses
+    let lists_any = extra_traits.iter().any(|t| t == "Any");
+    let mut rows: Vec<::proc_macro2::TokenStream> =
+        extra_traits.iter().map(|t| quote! { (dyn #t) }).collect();
+    // the automatic `dyn Any` row, `Self` again for unsized types
+    let any_row = match lists_any {
+        true => None,
+        false => {
+            rows.push(quote! { (dyn ::core::any::Any) });
+            Some(rows.len() - 1)
+        }
+    };
+    if send_sync.is_some() {
+        if !lists_any {
+            rows.push(quote! {
+                (dyn ::core::any::Any + ::core::marker::Send + ::core::marker::Sync)
+            });
+        }
+        for t in extra_traits.iter() {
+            rows.push(quote! {
+                (dyn #t + ::core::marker::Send + ::core::marker::Sync)
+            });
+        }
+    }
+    let row_bounds: Vec<::proc_macro2::TokenStream> = extra_traits
+        .iter()
+        .map(|t| quote! { #t })
+        .chain(any_row.map(|_| quote! { ::core::any::Any }))
+        .collect();
+    where_clause.predicates.push(::syn::parse_quote! {
+        #name #ty_generics: #(#row_bounds +)* ::any_trait::anyptr::Erasable
+    });
+
+    // fields marked `#[any_children]`
+    let children: Vec<::syn::Member> = items
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| {
+            f.attrs.iter().any(|a| a.path().is_ident("any_children"))
+        })
+        .map(|(idx, f)| match &f.ident {
+            Some(ident) => ::syn::Member::Named(ident.clone()),
+            None => ::syn::Member::Unnamed(::syn::Index::from(idx)),
+        })
+        .collect();
+    let children_fns = match children.is_empty() {
+        true => quote! {},
+        false => quote! {
+            fn children(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+                #(::any_trait::children::AnyChildren::for_each_child(
+                    &self.#children, f);
+                )*
             }
-            fn type_erase_mut(&mut self, trait_num: usize) -> ::any_trait::anyptr::AnyPtr {
-                const TRAITS : [::any_trait::typeidconst::TypeIdConst; #tot_traits] =
-                    ::any_trait::typeidconst::append_array::
-                        <#name, #extra_traits_num, #tot_traits>(
-                    /* waiting for const Ord on TypeId
-                    ::any_trait::typeidconst::sort_array(
-                        [#(::any_trait::typeidconst::TypeIdConst::of::
-                            <dyn #extra_traits>()),*])
-                    */
-                    [#(::any_trait::typeidconst::TypeIdConst::of::
-                        <dyn #extra_traits>()),*]
-                    );
-                // In the future only the second part of TRAITS will be ordered.
-                // but  that means that the macro does not know the index of
-                // a type in that list.
-                // create constants that we can `match` against.
-                #(const #trait_idx_name :usize =
-                    ::any_trait::typeidconst::find_in::
-                        <dyn #extra_traits, #tot_traits>(TRAITS);
+            fn children_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+                #(::any_trait::children::AnyChildren::for_each_child_mut(
+                    &mut self.#children, f);
                 )*
-                // Type-erase `self` into `AnyPtr`
-                match trait_num {
-                        0 => {
-                            let ptr = self as *mut dyn AnyTrait;
+            }
+        },
+    };
 
-                        let erased = ::any_trait::anyptr::AnyPtr::from_mut::<dyn AnyTrait>(ptr);
-                        return erased;
-                    },
-                    1 => {
-                        let ptr = self as *mut #name;
+    let rows_num: ::syn::Index = ::syn::Index::from(rows.len());
+    let tot_rows: ::syn::Index = ::syn::Index::from(2 + rows.len());
+    // the index of each row in `TYPE_IDS`
+    let row_idx: Vec<::syn::Index> =
+        (0..rows.len()).map(|i| ::syn::Index::from(2 + i)).collect();
 
-                        let erased = ::any_trait::anyptr::AnyPtr::from_mut::<#name>(ptr);
-                        return erased;
-                    }
-                    #(#trait_idx_name => {
-                        let ptr = self as *mut dyn #extra_traits;
+    // Unsized types can't be made into a `dyn Trait`: they list themselves
+    // instead of `dyn AnyTrait` and `dyn Any`.
+    // Only the type system knows if we are sized, see `anyptr::Sizedness`
+    let probe = quote! {
+        use ::any_trait::anyptr::{ProbeSized as _, ProbeUnsized as _};
+        let sizedness = &::any_trait::anyptr::Sizedness::<Self>::new();
+    };
+    let is_sized = quote! {
+        ::any_trait::anyptr::is_sized(&|| {
+            (&::any_trait::anyptr::Sizedness::<Self>::new()).probe()
+        })
+    };
 
-                        let erased = ::any_trait::anyptr::AnyPtr::from_mut::<dyn #extra_traits>(ptr);
-                        return erased;
-                    }
-                    )*
-                    _ => {
-                        panic!("AnyTrait: forced cast to wrong type idx")
+    // what goes in each row. Every row is erased by its index, so a type
+    // listed twice is still erased correctly at both indexes
+    let is_any_row = |i: usize| any_row == Some(i);
+    let row_ids: Vec<::proc_macro2::TokenStream> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| match is_any_row(i) {
+            true => quote! {
+                match #is_sized {
+                    true => ::any_trait::typeidconst::TypeIdConst::of::<#ty>(),
+                    false => ::any_trait::typeidconst::TypeIdConst::of::<Self>(),
+                }
+            },
+            false => quote! { ::any_trait::typeidconst::TypeIdConst::of::<#ty>() },
+        })
+        .collect();
+    let row_names: Vec<::proc_macro2::TokenStream> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| match is_any_row(i) {
+            true => quote! {
+                match #is_sized {
+                    true => ::any_trait::typeidconst::type_name::<#ty>(),
+                    false => ::any_trait::typeidconst::type_name::<Self>(),
+                }
+            },
+            false => quote! { ::any_trait::typeidconst::type_name::<#ty>() },
+        })
+        .collect();
+    let row_erase_mut: Vec<::proc_macro2::TokenStream> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| match is_any_row(i) {
+            true => quote! { sizedness.erase_any(ptr) },
+            false => quote! {
+                ::any_trait::anyptr::AnyPtr::from_mut::<#ty>(ptr as *mut #ty)
+            },
+        })
+        .collect();
+    let row_erase: Vec<::proc_macro2::TokenStream> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| match is_any_row(i) {
+            true => quote! { sizedness.erase_any(ptr) },
+            false => quote! {
+                ::any_trait::anyptr::AnyPtr::from::<#ty>(ptr as *const #ty)
+            },
+        })
+        .collect();
+
+    let vtables_impl = match vtables {
+        None => quote! {},
+        Some(_) => quote! {
+            impl ::any_trait::vtable::AnyVTables for #name {
+                fn vtables() -> &'static ::any_trait::vtable::VTables {
+                    fn vtable(trait_num: usize)
+                        -> ::core::option::Option<::any_trait::anyptr::AnyPtr>
+                    {
+                        // never dereferenced, we only want the vtables
+                        let ptr = ::core::ptr::NonNull::<#name>::dangling()
+                            .as_ptr() as *const #name;
+                        return match trait_num {
+                            0 => ::core::option::Option::Some(
+                                ::any_trait::anyptr::AnyPtr::from::<dyn AnyTrait>(
+                                    ptr as *const dyn AnyTrait)),
+                            #(#row_idx => ::core::option::Option::Some(
+                                ::any_trait::anyptr::AnyPtr::from::<#rows>(
+                                    ptr as *const #rows)),
+                            )*
+                            _ => ::core::option::Option::None,
+                        };
                     }
+                    static VTABLES: ::any_trait::vtable::VTables =
+                        ::any_trait::vtable::VTables::new::<#name>(vtable);
+                    &VTABLES
                 }
             }
-            fn type_erase(&self, trait_num: usize) -> ::any_trait::anyptr::AnyPtr {
-                const TRAITS : [::any_trait::typeidconst::TypeIdConst; #tot_traits] =
-                    ::any_trait::typeidconst::append_array::
-                        <#name, #extra_traits_num, #tot_traits>(
-                    /* waiting for const Ord on TypeId
+        },
+    };
+
+    let out = quote! {
+        impl #impl_generics ::any_trait::AnyTraitStatic for #name #ty_generics
+          #where_clause
+        {
+            const TYPE_IDS: &'static [::any_trait::typeidconst::TypeIdConst] = {
+                use ::any_trait::anyptr::{ProbeSized as _, ProbeUnsized as _};
+                &::any_trait::typeidconst::append_array::
+                    <Self, #rows_num, #tot_rows>(
+                    #is_sized,
+                    /* waiting for const Ord on TypeId...
                     ::any_trait::typeidconst::sort_array(
                         [#(::any_trait::typeidconst::TypeIdConst::of::
-                            <dyn #extra_traits>()),*])
+                            <#rows>()),*])
                     */
-                    [#(::any_trait::typeidconst::TypeIdConst::of::
-                        <dyn #extra_traits>()),*]
-                    );
-                // In the future only the second part of TRAITS will be ordered.
-                // but  that means that the macro does not know the index of
-                // a type in that list.
-                // create constants that we can `match` against.
-                #(const #trait_idx_name :usize =
-                    ::any_trait::typeidconst::find_in::
-                        <dyn #extra_traits, #tot_traits>(TRAITS);
-                )*
-                // Type-erase `self` into `AnyPtr`
-                match trait_num {
-                    0 => {
-                        let ptr = self as *const dyn AnyTrait;
-
-                        let erased = ::any_trait::anyptr::AnyPtr::from::<dyn AnyTrait>(ptr);
-                        return erased;
-                    },
-                    1 => {
-                        let ptr = self as *const #name;
-
-                        let erased = ::any_trait::anyptr::AnyPtr::from::<#name>(ptr);
-                        return erased;
-                    }
-                    #(#trait_idx_name => {
-                        let ptr = self as *const dyn #extra_traits;
-
-                        let erased = ::any_trait::anyptr::AnyPtr::from::<dyn #extra_traits>(ptr);
-                        return erased;
-                    }
-                    )*
-                    _ => {
-                        panic!("AnyTrait: forced cast to wrong type idx")
-                    }
-                }
+                    [#(#row_ids,)*]
+                )
+            };
+            // same order as `TYPE_IDS`
+            const TRAIT_NAMES: &'static [&'static str] = {
+                use ::any_trait::anyptr::{ProbeSized as _, ProbeUnsized as _};
+                &::any_trait::typeidconst::append_names::
+                    <Self, #rows_num, #tot_rows>(
+                    #is_sized,
+                    [#(#row_names,)*]
+                )
+            };
+        }
+        impl #impl_generics AnyTrait for #name #ty_generics
+          #where_clause
+        {
+            #[inline]
+            fn type_ids(&self) -> &'static [::any_trait::typeidconst::TypeIdConst] {
+                return <Self as ::any_trait::AnyTraitStatic>::TYPE_IDS;
+            }
+            #[inline]
+            fn trait_names(&self) -> &'static [&'static str] {
+                return <Self as ::any_trait::AnyTraitStatic>::TRAIT_NAMES;
+            }
+            #children_fns
+            #[inline]
+            fn upcast_anytrait(&self) -> &dyn AnyTrait {
+                #probe
+                return sizedness.upcast(self);
+            }
+            #[inline]
+            fn upcast_anytrait_mut(&mut self) -> &mut dyn AnyTrait {
+                #probe
+                return sizedness.upcast_mut(self);
+            }
+            fn type_erase_mut(&mut self, trait_num: usize) -> ::any_trait::anyptr::AnyPtr {
+                #probe
+                let ptr = self as *mut Self;
+                // Type-erase `self` into `AnyPtr`, by index in `TYPE_IDS`
+                return match trait_num {
+                    0 => sizedness.erase_anytrait(ptr),
+                    1 => ::any_trait::anyptr::AnyPtr::from_mut::<Self>(ptr),
+                    #(#row_idx => #row_erase_mut,)*
+                    _ => panic!("AnyTrait: forced cast to wrong type idx"),
+                };
+            }
+            fn type_erase(&self, trait_num: usize) -> ::any_trait::anyptr::AnyPtr {
+                #probe
+                // only ever read through: the erased pointer
+                // goes back to a shared reference
+                let ptr = self as *const Self as *mut Self;
+                // Type-erase `self` into `AnyPtr`, by index in `TYPE_IDS`
+                return match trait_num {
+                    0 => sizedness.erase_anytrait(ptr),
+                    1 => ::any_trait::anyptr::AnyPtr::from::<Self>(ptr),
+                    #(#row_idx => #row_erase,)*
+                    _ => panic!("AnyTrait: forced cast to wrong type idx"),
+                };
             }
         }
     };
+    let out = quote! {
+        #out
+        #vtables_impl
+    };
     TokenStream::from(out)
 }
@@ -0,0 +1,246 @@
+//! Adapt a trait to another one, for types that don't implement it
+//!
+//! **Requires the `alloc` feature**
+//!
+//! ```rust
+//! use any_trait::{
+//!     AnySubTrait, AnyTrait, adapter,
+//!     adapt::{AdapterRegistry},
+//! };
+//! trait OldLogger {
+//!     fn old_log(&self, msg: &str) -> usize;
+//! }
+//! trait Logger {
+//!     fn log(&self, msg: &str) -> usize;
+//! }
+//! struct LoggerShim<'a>(&'a dyn OldLogger);
+//! impl<'a> From<&'a dyn OldLogger> for LoggerShim<'a> {
+//!     fn from(old: &'a dyn OldLogger) -> Self {
+//!         LoggerShim(old)
+//!     }
+//! }
+//! impl Logger for LoggerShim<'_> {
+//!     fn log(&self, msg: &str) -> usize {
+//!         self.0.old_log(msg)
+//!     }
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(OldLogger)]
+//! struct Legacy {}
+//! impl OldLogger for Legacy {
+//!     fn old_log(&self, msg: &str) -> usize {
+//!         msg.len()
+//!     }
+//! }
+//!
+//! let mut adapters = AdapterRegistry::new();
+//! adapters.register(adapter!(dyn OldLogger => dyn Logger, LoggerShim));
+//!
+//! let legacy = Legacy {};
+//! let logger = adapters.cast_ref_or_adapt::<dyn Logger, _>(&legacy).unwrap();
+//! assert_eq!(logger.log("hello"), 5);
+//! ```
+//!
+//! The shim must implement `From<&'a dyn Old>` and `New` for any `'a`.
+//!
+//! Adapters are built with the `adapter!` macro, and not with a plain
+//! generic function: a generic `dyn New` is always `dyn New + 'static`,
+//! but the shim borrows the object, so only the macro can name
+//! `dyn New + 'a`.
+
+use crate::{AnyTrait, AnyTraitCast, anyref::AnyRef, typeidconst::TypeIdConst};
+/// for `adapter!`, the caller might not have `extern crate alloc`
+#[doc(hidden)]
+pub use ::alloc::boxed::Box;
+use ::alloc::vec::Vec;
+use ::core::{any::Any, marker::PhantomData, ops::Deref};
+
+/// `D` borrowed from an object: either the object itself, or a shim
+/// that adapts it
+pub struct Adapted<'a, D: ?Sized + 'static> {
+    inner: Inner<'a, D>,
+}
+
+enum Inner<'a, D: ?Sized + 'static> {
+    Native(&'a D),
+    /// The shim borrows from the object for `'a`, but `D` is `'static`.
+    /// We never give out the `Box` or a reference that outlives `'a`
+    Shim(Box<D>, PhantomData<&'a ()>),
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, D: ?Sized + 'static> Adapted<'a, D> {
+    /// The object implements `D` itself
+    pub fn native(d: &'a D) -> Self {
+        return Self {
+            inner: Inner::Native(d),
+        };
+    }
+
+    /// **don't use. internal only, see `adapter!`**
+    ///
+    /// # Safety
+    /// `shim` must be a `Box<D + 'a>` with the lifetime erased,
+    /// built from a shim that implements `D` for any lifetime
+    #[doc(hidden)]
+    #[allow(unsafe_code)]
+    pub unsafe fn from_erased_shim(shim: Box<D>) -> Self {
+        return Self {
+            inner: Inner::Shim(shim, PhantomData),
+        };
+    }
+
+    /// `true` if this is the object itself, `false` if it is a shim
+    pub fn is_native(&self) -> bool {
+        return matches!(self.inner, Inner::Native(_));
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, D: ?Sized + 'static> Deref for Adapted<'a, D> {
+    type Target = D;
+    fn deref(&self) -> &D {
+        return match &self.inner {
+            Inner::Native(d) => d,
+            Inner::Shim(shim, _) => shim,
+        };
+    }
+}
+
+/// Builds a `To` shim out of an object that can be cast to another type
+///
+/// Use `adapter!` to create one
+pub struct Adapter<To: ?Sized + 'static> {
+    from: TypeIdConst,
+    adapt: for<'a> fn(AnyRef<'a>) -> Option<Adapted<'a, To>>,
+}
+
+#[allow(clippy::needless_return)]
+impl<To: ?Sized + 'static> Adapter<To> {
+    /// `adapt` gets the object as `From`, erased
+    pub fn new<From: ?Sized + 'static>(
+        adapt: for<'a> fn(AnyRef<'a>) -> Option<Adapted<'a, To>>,
+    ) -> Self {
+        return Self {
+            from: TypeIdConst::of::<From>(),
+            adapt,
+        };
+    }
+}
+
+/// Build an `Adapter` from `dyn Old` to `dyn New` with a shim type
+///
+/// example: `adapter!(dyn OldLogger => dyn Logger, LoggerShim)`
+///
+/// `LoggerShim<'a>` must implement `From<&'a dyn OldLogger>`,
+/// and `Logger` for any `'a`
+#[macro_export]
+macro_rules! adapter {
+    (dyn $from:path => dyn $to:path, $shim:ty) => {{
+        fn adapt<'a>(
+            from: $crate::anyref::AnyRef<'a>,
+        ) -> ::core::option::Option<$crate::adapt::Adapted<'a, dyn $to>>
+        {
+            let from = from.downcast::<dyn $from>()?;
+            // the coercion proves that the shim implements the trait
+            // for any `'a`, so erasing the lifetime is fine
+            let shim: $crate::adapt::Box<dyn $to + 'a> =
+                $crate::adapt::Box::new(<$shim>::from(from));
+            #[allow(unsafe_code)]
+            unsafe {
+                let shim = ::core::mem::transmute::<
+                    $crate::adapt::Box<dyn $to + 'a>,
+                    $crate::adapt::Box<dyn $to + 'static>,
+                >(shim);
+                return ::core::option::Option::Some(
+                    $crate::adapt::Adapted::from_erased_shim(shim),
+                );
+            }
+        }
+        $crate::adapt::Adapter::<dyn $to>::new::<dyn $from>(adapt)
+    }};
+}
+
+struct Entry {
+    from: TypeIdConst,
+    to: TypeIdConst,
+    /// `Adapter<To>`
+    adapter: Box<dyn Any>,
+}
+
+/// Registered adapters, searched in registration order
+pub struct AdapterRegistry {
+    adapters: Vec<Entry>,
+}
+
+#[allow(clippy::needless_return)]
+impl AdapterRegistry {
+    pub const fn new() -> Self {
+        return Self {
+            adapters: Vec::new(),
+        };
+    }
+
+    /// Add an adapter, see `adapter!`
+    pub fn register<To: ?Sized + 'static>(&mut self, adapter: Adapter<To>) {
+        self.adapters.push(Entry {
+            from: adapter.from,
+            to: TypeIdConst::of::<To>(),
+            adapter: Box::new(adapter),
+        });
+    }
+
+    /// Check if `obj` can be cast or adapted to `D`
+    pub fn can_adapt<D, T>(&self, obj: &T) -> bool
+    where
+        D: ?Sized + 'static,
+        T: AnyTrait + ?Sized,
+    {
+        if obj.implements::<D>() {
+            return true;
+        }
+        let to = TypeIdConst::of::<D>();
+        return self
+            .adapters
+            .iter()
+            .any(|e| e.to == to && obj.type_ids().contains(&e.from));
+    }
+
+    /// Cast `obj` to `D` if it implements it, otherwise use the first
+    /// adapter that can build a `D` out of `obj`
+    pub fn cast_ref_or_adapt<'a, D, T>(
+        &self,
+        obj: &'a T,
+    ) -> Option<Adapted<'a, D>>
+    where
+        D: ?Sized + 'static,
+        T: AnyTrait + ?Sized,
+    {
+        if let Some(d) = obj.cast_ref::<D>() {
+            return Some(Adapted::native(d));
+        }
+        let to = TypeIdConst::of::<D>();
+        for e in self.adapters.iter().filter(|e| e.to == to) {
+            let Some(trait_idx) =
+                obj.type_ids().iter().position(|t| *t == e.from)
+            else {
+                continue;
+            };
+            let adapter = e
+                .adapter
+                .downcast_ref::<Adapter<D>>()
+                .expect("AdapterRegistry: adapter registered as wrong type");
+            if let Some(adapted) = (adapter.adapt)(obj.erase_ref(trait_idx)) {
+                return Some(adapted);
+            }
+        }
+        return None;
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl Default for AdapterRegistry {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
@@ -0,0 +1,249 @@
+//! One instance per concrete type, with trait queries
+//!
+//! **Requires the `alloc` feature**
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, anymap::AnyTraitMap};
+//! trait Reload {
+//!     fn reload(&mut self);
+//! }
+//! #[derive(AnySubTrait, Default)]
+//! #[any_sub_trait(Reload)]
+//! struct Config {
+//!     loaded: usize,
+//! }
+//! impl Reload for Config {
+//!     fn reload(&mut self) {
+//!         self.loaded += 1;
+//!     }
+//! }
+//! #[derive(AnySubTrait)]
+//! struct Time(u64);
+//!
+//! let mut resources = AnyTraitMap::new();
+//! resources.insert(Time(0));
+//! resources.entry::<Config>().or_default();
+//!
+//! for r in resources.iter_cast_mut::<dyn Reload>() {
+//!     r.reload();
+//! }
+//! assert_eq!(resources.get::<Config>().unwrap().loaded, 1);
+//! ```
+//!
+//! The map is keyed on the concrete type (`type_ids()[1]`).\
+//! The keys are kept in a plain list, so finding a concrete type is linear
+//! in the number of types: maps usually hold a handful of resources, and
+//! `core` has no hash map.
+//! Trait queries use the per-trait indexes of `AnyVec`.
+
+use crate::{
+    AnyTrait, AnyTraitCast,
+    anyvec::{self, AnyVec, Handle},
+    typeidconst::TypeIdConst,
+};
+use ::alloc::{boxed::Box, vec::Vec};
+use ::core::marker::PhantomData;
+
+/// `Box<dyn AnyTrait>` to `Box<T>`, if `T` is the concrete type
+#[allow(clippy::needless_return)]
+fn downcast_box<T: AnyTrait>(
+    value: Box<dyn AnyTrait>,
+) -> Result<Box<T>, Box<dyn AnyTrait>> {
+    let raw = Box::into_raw(value);
+    // SAFETY: `raw` comes from a `Box` and we give it back to one: either
+    // erased as the concrete type `T`, or untouched
+    #[allow(unsafe_code)]
+    unsafe {
+        // the concrete type is always at index 1
+        return match (*raw).erase_mut(1).downcast::<T>() {
+            Some(t) => Ok(Box::from_raw(t as *mut T)),
+            None => Err(Box::from_raw(raw)),
+        };
+    }
+}
+
+/// Map of concrete type -> instance of that type
+pub struct AnyTraitMap {
+    values: AnyVec,
+    /// concrete type of every value
+    keys: Vec<(TypeIdConst, Handle)>,
+}
+
+#[allow(clippy::needless_return)]
+impl AnyTraitMap {
+    pub const fn new() -> Self {
+        return Self {
+            values: AnyVec::new(),
+            keys: Vec::new(),
+        };
+    }
+
+    /// Number of types in the map
+    pub fn len(&self) -> usize {
+        return self.keys.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.keys.is_empty();
+    }
+
+    fn handle(&self, type_id: TypeIdConst) -> Option<Handle> {
+        let (_, handle) = self.keys.iter().find(|(t, _)| *t == type_id)?;
+        return Some(*handle);
+    }
+
+    /// Check if there is a value of type `T`
+    pub fn contains<T: AnyTrait>(&self) -> bool {
+        return self.handle(TypeIdConst::of::<T>()).is_some();
+    }
+
+    /// Insert a value, returning the old one of the same type
+    pub fn insert<T: AnyTrait>(&mut self, value: T) -> Option<T> {
+        if let Some(old) = self.get_mut::<T>() {
+            return Some(::core::mem::replace(old, value));
+        }
+        self.insert_new(Box::new(value));
+        return None;
+    }
+
+    /// Insert a boxed value, keyed on its concrete type.
+    ///
+    /// Returns the old value of the same type
+    pub fn insert_box(
+        &mut self,
+        value: Box<dyn AnyTrait>,
+    ) -> Option<Box<dyn AnyTrait>> {
+        let old = self.remove_type(value.type_ids()[1]);
+        self.insert_new(value);
+        return old;
+    }
+
+    /// the concrete type of `value` must not be in the map
+    fn insert_new(&mut self, value: Box<dyn AnyTrait>) -> Handle {
+        let type_id = value.type_ids()[1];
+        let handle = self.values.insert_box(value);
+        self.keys.push((type_id, handle));
+        return handle;
+    }
+
+    fn remove_type(
+        &mut self,
+        type_id: TypeIdConst,
+    ) -> Option<Box<dyn AnyTrait>> {
+        let pos = self.keys.iter().position(|(t, _)| *t == type_id)?;
+        let (_, handle) = self.keys.swap_remove(pos);
+        return self.values.remove(handle);
+    }
+
+    /// Remove the value of type `T`
+    pub fn remove<T: AnyTrait>(&mut self) -> Option<T> {
+        let old = self.remove_type(TypeIdConst::of::<T>())?;
+        return match downcast_box::<T>(old) {
+            Ok(old) => Some(*old),
+            Err(_) => unreachable!("AnyTraitMap: value under the wrong key"),
+        };
+    }
+
+    /// Get the value of type `T`
+    pub fn get<T: AnyTrait>(&self) -> Option<&T> {
+        let handle = self.handle(TypeIdConst::of::<T>())?;
+        // the concrete type is always at index 1, no need to search
+        return self.values.get_any(handle)?.erase_ref(1).downcast::<T>();
+    }
+
+    /// Get the value of type `T`
+    pub fn get_mut<T: AnyTrait>(&mut self) -> Option<&mut T> {
+        let handle = self.handle(TypeIdConst::of::<T>())?;
+        return self
+            .values
+            .get_any_mut(handle)?
+            .erase_mut(1)
+            .downcast::<T>();
+    }
+
+    /// Get the entry of type `T`, for in-place manipulation
+    pub fn entry<T: AnyTrait>(&mut self) -> Entry<'_, T> {
+        return Entry {
+            map: self,
+            _t: PhantomData,
+        };
+    }
+
+    /// Iterate over all the values as `&dyn AnyTrait`
+    pub fn iter(&self) -> anyvec::Iter<'_, dyn AnyTrait> {
+        return self.values.iter::<dyn AnyTrait>();
+    }
+
+    /// Iterate over all the values that can be cast to `D`
+    pub fn iter_cast<D: ?Sized + 'static>(&self) -> anyvec::Iter<'_, D> {
+        return self.values.iter::<D>();
+    }
+
+    /// Iterate mutably over all the values that can be cast to `D`
+    pub fn iter_cast_mut<D: ?Sized + 'static>(
+        &mut self,
+    ) -> anyvec::IterMut<'_, D> {
+        return self.values.iter_mut::<D>();
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl Default for AnyTraitMap {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// The value of type `T` in an `AnyTraitMap`, which may or may not exist
+///
+/// see `AnyTraitMap::entry`
+pub struct Entry<'a, T> {
+    map: &'a mut AnyTraitMap,
+    _t: PhantomData<T>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, T: AnyTrait> Entry<'a, T> {
+    /// Check if there is a value of type `T`
+    pub fn is_occupied(&self) -> bool {
+        return self.map.contains::<T>();
+    }
+
+    /// Modify the value, if there is one
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Self {
+        if let Some(value) = self.map.get_mut::<T>() {
+            f(value);
+        }
+        return self;
+    }
+
+    /// Insert `default` if there is no value, and return the value
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        return self.or_insert_with(|| default);
+    }
+
+    /// Insert the result of `default()` if there is no value,
+    /// and return the value
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        let handle = match self.map.handle(TypeIdConst::of::<T>()) {
+            Some(handle) => handle,
+            None => self.map.insert_new(Box::new(default())),
+        };
+        return self
+            .map
+            .values
+            .get_any_mut(handle)
+            .expect("AnyTraitMap: missing value")
+            .erase_mut(1)
+            .downcast::<T>()
+            .expect("AnyTraitMap: value under the wrong key");
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, T: AnyTrait + Default> Entry<'a, T> {
+    /// Insert `T::default()` if there is no value, and return the value
+    pub fn or_default(self) -> &'a mut T {
+        return self.or_insert_with(T::default);
+    }
+}
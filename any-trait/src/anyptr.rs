@@ -10,49 +10,144 @@
 //! [**quinedot**](https://users.rust-lang.org/t/cast-from-concrete-to-any-and-subtraits/136086/4?u=lucafulchir)
 //! This is basically copied from his response
 
+#[cfg(not(feature = "ptr_metadata"))]
+use ::core::mem::MaybeUninit;
 use ::core::{
-    mem::{MaybeUninit, size_of, transmute_copy},
-    ptr::NonNull,
+    any::Any,
+    marker::PhantomData,
+    mem::{size_of, transmute_copy},
+    ptr::{DynMetadata, NonNull, Pointee},
 };
 
-/// A pointer to a `Sized` type or to a `dyn Trait`.
+use crate::AnyTrait;
+
+#[cfg(any(feature = "checked", debug_assertions))]
+use crate::typeidconst::TypeIdConst;
+
+/// A pointer to a `Sized` type, to a `dyn Trait` or to a slice-like type.
 ///
 /// modified from:
 /// [**quinedot**](https://users.rust-lang.org/t/cast-from-concrete-to-any-and-subtraits/136086/4?u=lucafulchir)
 ///
 /// Rust has two main type of pointers:
 /// * thin pointer: same size as `usize`. we store those directly in `.data`
-/// * fat pointers: they have a `data` pointer and a `vtable` pointer.
+/// * fat pointers: they have a `data` pointer and a metadata word:
+///   * a `vtable` pointer for `dyn Trait`
+///   * a length for `[T]`, `str` and structs whose last field is one of those
+///
+/// The two kinds of fat pointers can't be told apart at runtime, so
+/// `AnyPtr::from` looks at the type of the metadata (see `Erasable`).
 ///
 /// Unfortunately in fat pointers there is no guerantee on which is first
-/// (data or vtable), so we need to check every time.\
-/// We store this information as a 1-byte offset in the `.meta` field.\
-/// Vtable pointers are guaranteed to be word-aligned.
-#[derive(Copy, Clone, Debug)]
+/// (data or metadata), so we need to check every time.\
+/// For vtables we store this information as a 1-byte offset in the `.meta`
+/// field, since vtable pointers are guaranteed to be word-aligned.\
+/// Lengths can be anything, so we just remember the order.
+///
+/// With the `ptr_metadata` feature we don't probe the layout at all:
+/// we split the pointer with `core::ptr::metadata` and rebuild it with
+/// `NonNull::from_raw_parts`, without touching any address.
+///
+/// With the `checked` feature (or with `debug_assertions`) we also carry
+/// the `TypeIdConst` of the original pointer, and `to_ptr` will panic
+/// instead of silently returning a pointer of the wrong type.\
+/// That needs `T: 'static`, which is then required in every mode:
+/// turning `checked` on must not break a build.
+#[derive(Copy, Clone)]
 pub struct AnyPtr {
     /// Pointer to the value.
     ///
-    /// * If this is a thin pointer, `.meta` will be `None`
+    /// * If this is a thin pointer, `.meta` will be `Meta::None`
     /// * If this is a fat pointer, this is the `data` part of the fat pointer
     data: NonNull<()>,
 
+    /// Metadata of the fat pointer, if any
+    meta: Meta,
+
+    /// Type of the pointer we were created from.
+    ///
+    /// Only used to validate `to_ptr`, release builds don't pay for it.
+    #[cfg(any(feature = "checked", debug_assertions))]
+    type_id: TypeIdConst,
+}
+
+/// Metadata of a type-erased pointer
+#[derive(Copy, Clone, Debug)]
+enum Meta {
+    /// Thin pointer, no metadata
+    None,
     /// Pointer to a `dyn Trait` vtable.
     ///
-    /// If this is `None`, `AnyPtr` is a thin pointer.
-    /// If this is `Some()` then we have a fat pointer, and we have to
-    /// check the alignment of the vtable.
+    /// Without the `ptr_metadata` feature we have to check the alignment
+    /// of the vtable:
     ///
     /// * vtable %2 == 0 => the vtable comse first, the data ptr second
     /// * vtable %2 == 1 => the data ptr comse first, the vtable second
-    meta: Option<NonNull<()>>,
+    VTable(NonNull<()>),
+    /// Length of a slice, a `str` or of a type ending with one of those
+    Len {
+        len: usize,
+        /// Only needed when we probe the layout
+        #[cfg(not(feature = "ptr_metadata"))]
+        data_first: bool,
+    },
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for () {}
+    impl<D: ?Sized> Sealed for ::core::ptr::DynMetadata<D> {}
+    impl Sealed for usize {}
 }
 
+/// Pointer metadata that `AnyPtr` knows how to store:
+/// nothing, a vtable or a length
+///
+/// Sealed, since a wrong `IS_LEN` would corrupt the pointer
+pub trait PtrMetadata: sealed::Sealed {
+    /// The metadata is a length and not a vtable
+    const IS_LEN: bool;
+}
+impl PtrMetadata for () {
+    const IS_LEN: bool = false;
+}
+impl<D: ?Sized> PtrMetadata for DynMetadata<D> {
+    const IS_LEN: bool = false;
+}
+impl PtrMetadata for usize {
+    const IS_LEN: bool = true;
+}
+
+/// Types that can be pointed to by an `AnyPtr`:
+/// `Sized` types, `dyn Trait` and slice-like types
+///
+/// Implemented for every type whose metadata is a `PtrMetadata`,
+/// which today is every type.\
+/// This relies on the nightly `Pointee` trait with or without the
+/// `ptr_metadata` cargo feature, which only selects how `AnyPtr` splits
+/// the pointer
+pub trait Erasable: Pointee<Metadata: PtrMetadata> {}
+impl<T: ?Sized + Pointee<Metadata: PtrMetadata>> Erasable for T {}
+
+impl ::core::fmt::Debug for AnyPtr {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        let mut out = f.debug_struct("AnyPtr");
+        out.field("data", &self.data).field("meta", &self.meta);
+        #[cfg(any(feature = "checked", debug_assertions))]
+        out.field("type_id", &self.type_id);
+        out.finish()
+    }
+}
+
+#[allow(clippy::needless_return)]
 impl AnyPtr {
-    /// Create a type-erased mutable pointer
+    /// Create a type-erased pointer
+    ///
+    /// `T` can be `Sized`, a `dyn Trait` or a slice-like type
     ///
     /// # Panics
     /// If `ptr` is Null.
-    pub fn from<T: ?Sized>(ptr: *const T) -> Self {
+    pub fn from<T: ?Sized + Erasable + 'static>(ptr: *const T) -> Self {
         const {
             assert!(
                 size_of::<*const T>() == size_of::<NonNull<()>>()
@@ -63,23 +158,98 @@ impl AnyPtr {
         return Self::from_mut::<T>(ptr as *mut T);
     }
 
-    /// Create a type-erased pointer
+    /// Create a type-erased mutable pointer
+    ///
+    /// `T` can be `Sized`, a `dyn Trait` or a slice-like type
     ///
     /// # Panics
     /// If `ptr` is Null.
-    pub fn from_mut<T: ?Sized>(ptr: *mut T) -> Self {
+    pub fn from_mut<T: ?Sized + Erasable + 'static>(ptr: *mut T) -> Self {
         const {
             assert!(
                 size_of::<*const T>() == size_of::<NonNull<()>>()
                     || size_of::<*const T>() == size_of::<[*mut (); 2]>()
             )
         }
+        let (data, meta) = Self::split::<T>(ptr);
+
+        Self {
+            data,
+            meta,
+            #[cfg(any(feature = "checked", debug_assertions))]
+            type_id: TypeIdConst::of::<T>(),
+        }
+    }
+
+    /// Create a type-erased pointer to a slice-like type
+    ///
+    /// Same as `AnyPtr::from`, but only for types with a length as
+    /// metadata: `[U]`, `str` or a struct whose last field is one of those
+    ///
+    /// # Panics
+    /// If `ptr` is Null.
+    pub fn from_slice<T: ?Sized + Pointee<Metadata = usize> + 'static>(
+        ptr: *const T,
+    ) -> Self {
+        return Self::from_mut::<T>(ptr as *mut T);
+    }
+
+    /// Create a type-erased mutable pointer to a slice-like type
+    ///
+    /// Same as `AnyPtr::from_mut`, but only for types with a length as
+    /// metadata: `[U]`, `str` or a struct whose last field is one of those
+    ///
+    /// # Panics
+    /// If `ptr` is Null.
+    pub fn from_slice_mut<T: ?Sized + Pointee<Metadata = usize> + 'static>(
+        ptr: *mut T,
+    ) -> Self {
+        return Self::from_mut::<T>(ptr);
+    }
+
+    /// Split a pointer in data and metadata, with `core::ptr::metadata`
+    ///
+    /// # Panics
+    /// If `ptr` is Null.
+    #[cfg(feature = "ptr_metadata")]
+    fn split<T: ?Sized + Erasable>(ptr: *mut T) -> (NonNull<()>, Meta) {
+        const {
+            assert!(
+                size_of::<<T as Pointee>::Metadata>() == 0
+                    || size_of::<<T as Pointee>::Metadata>()
+                        == size_of::<NonNull<()>>()
+            )
+        }
+        let data = NonNull::new(ptr.cast::<()>()).unwrap();
+        if size_of::<<T as Pointee>::Metadata>() == 0 {
+            return (data, Meta::None);
+        }
+        let meta = ::core::ptr::metadata(ptr);
+        if <T::Metadata as PtrMetadata>::IS_LEN {
+            // SAFETY: the metadata of slice-like types is a `usize`
+            let len = unsafe {
+                transmute_copy::<<T as Pointee>::Metadata, usize>(&meta)
+            };
+            return (data, Meta::Len { len });
+        }
+        // SAFETY: the metadata of `dyn Trait` is a `DynMetadata`, which is
+        // just a pointer to the vtable, so this keeps its provenance.
+        let vtable = unsafe {
+            transmute_copy::<<T as Pointee>::Metadata, *mut ()>(&meta)
+        };
+        let vtable = NonNull::new(vtable).expect("AnyPtr: null vtable");
+        return (data, Meta::VTable(vtable));
+    }
+
+    /// Split a pointer in data and metadata by probing the fat pointer layout
+    ///
+    /// # Panics
+    /// If `ptr` is Null.
+    #[cfg(not(feature = "ptr_metadata"))]
+    fn split<T: ?Sized + Erasable>(ptr: *mut T) -> (NonNull<()>, Meta) {
         if size_of::<*const T>() == size_of::<NonNull<()>>() {
             // THIN pointer
-            return Self {
-                data: NonNull::new(ptr).unwrap().cast(),
-                meta: None,
-            };
+            return (NonNull::new(ptr).unwrap().cast(), Meta::None);
         }
 
         // FAT pointer
@@ -98,29 +268,72 @@ impl AnyPtr {
             )
         };
         let data = NonNull::new(ptr as *mut ()).unwrap();
-        let meta = if before[0] == after[0] {
+        let data_first = if before[0] == after[0] {
             // The data pointer should have been added to.
             debug_assert_eq!(before[1] as usize, after[1] as usize - 1);
-            // Vtable pointers must be non-null and word-aligned.
-            debug_assert_ne!(0, before[0] as usize);
-            debug_assert_eq!(0, before[0] as usize % 2);
-
-            // It was the first pointer so we store it directly.
-            NonNull::new(before[0])
+            false
         } else if before[1] == after[1] {
             // The data pointer should have been added to.
             debug_assert_eq!(before[0] as usize, after[0] as usize - 1);
-            // Vtable pointers must be non-null and word-aligned.
-            debug_assert_ne!(0, before[1] as usize);
-            debug_assert_eq!(0, before[1] as usize % 2);
-
-            // It was the second pointer so we flip the bottom bit.
-            NonNull::new(before[1].wrapping_byte_add(1))
+            true
         } else {
             unreachable!()
         };
+        let word = match data_first {
+            false => before[0],
+            true => before[1],
+        };
+        if <T::Metadata as PtrMetadata>::IS_LEN {
+            // Lengths have no alignment, just remember the order
+            let meta = Meta::Len {
+                len: word.addr(),
+                data_first,
+            };
+            return (data, meta);
+        }
+        // Vtable pointers must be non-null and word-aligned.
+        debug_assert_ne!(0, word as usize, "AnyPtr: null vtable");
+        debug_assert_eq!(0, word as usize % 2, "AnyPtr: unaligned vtable");
+        let vtable = match data_first {
+            // It was the first pointer so we store it directly.
+            false => NonNull::new(word),
+            // It was the second pointer so we flip the bottom bit.
+            true => NonNull::new(word.wrapping_byte_add(1)),
+        };
 
-        Self { data, meta }
+        return (data, Meta::VTable(vtable.unwrap()));
+    }
+
+    /// Address of the value, without the metadata
+    pub fn data(&self) -> NonNull<()> {
+        return self.data;
+    }
+
+    /// Address of the vtable, if this was created from a `dyn Trait`
+    pub fn vtable(&self) -> Option<NonNull<()>> {
+        let Meta::VTable(vtable) = self.meta else {
+            return None;
+        };
+        #[cfg(feature = "ptr_metadata")]
+        return Some(vtable);
+        // see `split`: the bottom bit tells us the order
+        #[cfg(not(feature = "ptr_metadata"))]
+        return match vtable.addr().get() % 2 {
+            0 => Some(vtable),
+            _ => NonNull::new(vtable.as_ptr().wrapping_byte_sub(1)),
+        };
+    }
+
+    /// Panic if `self` was not created from a pointer of type `T`.
+    ///
+    /// Only available with the `checked` feature or with `debug_assertions`
+    #[cfg(any(feature = "checked", debug_assertions))]
+    #[track_caller]
+    pub fn assert_type(&self, type_id: TypeIdConst) {
+        assert!(
+            self.type_id == type_id,
+            "AnyPtr: type-erased pointer does not match the expected type"
+        );
     }
 
     /// Convert this pointer into a `NonNull<T>`.
@@ -128,50 +341,230 @@ impl AnyPtr {
     /// # Safety
     ///
     /// `self` **MUST** have been created by a call to either:
-    /// * `AnyPtr::of::<T>(ptr)`
-    /// * `AnyPtr::of_mut::<T>(ptr)`
-    pub unsafe fn to_ptr<T: ?Sized>(self) -> NonNull<T> {
+    /// * `AnyPtr::from::<T>(ptr)`
+    /// * `AnyPtr::from_mut::<T>(ptr)`
+    /// * `AnyPtr::from_slice::<T>(ptr)`
+    /// * `AnyPtr::from_slice_mut::<T>(ptr)`
+    ///
+    /// # Panics
+    /// With the `checked` feature or with `debug_assertions`, if `self`
+    /// was not created from a `T` pointer
+    #[track_caller]
+    pub unsafe fn to_ptr<T: ?Sized + 'static>(self) -> NonNull<T> {
         const {
             assert!(
                 size_of::<*const T>() == size_of::<NonNull<T>>()
                     || size_of::<*const T>() == size_of::<[*mut (); 2]>()
             )
         }
+        #[cfg(any(feature = "checked", debug_assertions))]
+        self.assert_type(TypeIdConst::of::<T>());
+        // SAFETY: same requirements as ours
+        unsafe {
+            return self.join::<T>();
+        }
+    }
+
+    /// Rebuild the pointer from data and metadata with
+    /// `NonNull::from_raw_parts`
+    ///
+    /// # Safety
+    /// same as `to_ptr`
+    #[cfg(feature = "ptr_metadata")]
+    unsafe fn join<T: ?Sized>(self) -> NonNull<T> {
+        type Metadata<T> = <T as Pointee>::Metadata;
+        let meta = match self.meta {
+            Meta::None => {
+                assert_eq!(size_of::<Metadata<T>>(), 0);
+                // SAFETY: zero-sized, there is nothing to read
+                unsafe { transmute_copy::<(), Metadata<T>>(&()) }
+            }
+            Meta::VTable(vtable) => {
+                assert_eq!(size_of::<Metadata<T>>(), size_of::<NonNull<()>>());
+                // SAFETY: this is the reverse of `split::<T>`
+                unsafe { transmute_copy::<NonNull<()>, Metadata<T>>(&vtable) }
+            }
+            Meta::Len { len } => {
+                assert_eq!(size_of::<Metadata<T>>(), size_of::<usize>());
+                // SAFETY: this is the reverse of `split::<T>`
+                unsafe { transmute_copy::<usize, Metadata<T>>(&len) }
+            }
+        };
+        return NonNull::from_raw_parts(self.data, meta);
+    }
+
+    /// Rebuild the pointer from data and metadata by reversing the probed
+    /// layout
+    ///
+    /// # Safety
+    /// same as `to_ptr`
+    #[cfg(not(feature = "ptr_metadata"))]
+    unsafe fn join<T: ?Sized>(self) -> NonNull<T> {
         let mut slot = MaybeUninit::<NonNull<T>>::uninit();
-        if let Some(meta) = self.meta {
-            assert_eq!(size_of::<*const T>(), size_of::<[*mut (); 2]>(),);
-            // We do the reverse convertion from the end of `of_unsized`.
-            let ptr = match meta.addr().get() % 2 {
-                0 => [meta.as_ptr(), self.data.as_ptr()],
-                1 => [self.data.as_ptr(), meta.as_ptr().wrapping_byte_sub(1)],
-                _ => unreachable!(),
-            };
+        let ptr = match self.meta {
+            Meta::None => {
+                assert_eq!(size_of::<*const T>(), size_of::<NonNull<T>>());
+                assert_eq!(size_of::<NonNull<T>>(), size_of::<NonNull<()>>());
+                // SAFETY: We only have `Meta::None` when `T: Sized`
+                // and thus the size of `NonNull<()>` is the size of
+                // `NonNull<T>`.
+                let ptr = unsafe {
+                    transmute_copy::<NonNull<()>, NonNull<T>>(&self.data)
+                };
 
-            // SAFETY: We only have `meta == None` when `T: Sized`
-            // and thus the size of `NonNull<[*mut (); 2]>` is the size
-            // of `NonNull<T>`.
-            let ptr =
-                unsafe { transmute_copy::<[*mut (); 2], NonNull<T>>(&ptr) };
+                slot.write(ptr);
+                // SAFETY: We just initialized the data.
+                return unsafe { slot.assume_init() };
+            }
+            Meta::VTable(meta) => {
+                // We do the reverse convertion from the end of `split`.
+                match meta.addr().get() % 2 {
+                    0 => [meta.as_ptr(), self.data.as_ptr()],
+                    1 => {
+                        [self.data.as_ptr(), meta.as_ptr().wrapping_byte_sub(1)]
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Meta::Len { len, data_first } => {
+                let len = ::core::ptr::without_provenance_mut::<()>(len);
+                match data_first {
+                    false => [len, self.data.as_ptr()],
+                    true => [self.data.as_ptr(), len],
+                }
+            }
+        };
+        assert_eq!(size_of::<*const T>(), size_of::<[*mut (); 2]>(),);
 
-            slot.write(ptr);
+        // SAFETY: We only have fat metadata when `T: !Sized`
+        // and thus the size of `NonNull<[*mut (); 2]>` is the size
+        // of `NonNull<T>`.
+        let ptr = unsafe { transmute_copy::<[*mut (); 2], NonNull<T>>(&ptr) };
 
-            // SAFETY: We just initialized the data.
-            // We have also reconstructed the pointer data with the same
-            // values and in the same order as the original created in
-            // `of_unsized::<T>`, thus preserving vtable invariants.
-            unsafe { slot.assume_init() }
-        } else {
-            assert_eq!(size_of::<*const T>(), size_of::<NonNull<T>>());
-            assert_eq!(size_of::<NonNull<T>>(), size_of::<NonNull<()>>());
-            // SAFETY: We only have `meta == None` when `T: Sized`
-            // and thus the size of `NonNull<()>` is the size of `NonNull<T>`.
-            let ptr = unsafe {
-                transmute_copy::<NonNull<()>, NonNull<T>>(&self.data)
-            };
+        slot.write(ptr);
 
-            slot.write(ptr);
-            // SAFETY: We just initialized the data.
-            unsafe { slot.assume_init() }
-        }
+        // SAFETY: We just initialized the data.
+        // We have also reconstructed the pointer data with the same
+        // values and in the same order as the original created in
+        // `split::<T>`, thus preserving vtable invariants.
+        unsafe { slot.assume_init() }
+    }
+}
+
+/// **don't use. internal only, for the derive**
+///
+/// Unsized types can't be made into a `dyn Trait`, not even `dyn AnyTrait`
+/// or `dyn Any`. The derive can't tell if a type is sized, so it asks the
+/// type system with autoref specialization:
+/// `(&Sizedness::<T>::new()).erase_any(ptr)` resolves to `ProbeSized`
+/// if `T: Sized` can be proven, and to `ProbeUnsized` otherwise.
+/// The derive rejects `?Sized` type parameters, so this is the same for
+/// every instance of a generic type.\
+/// In const context use `is_sized` on a closure calling `.probe()`.
+/// The list of types and the type erasure use the same probe,
+/// so they always agree.
+#[doc(hidden)]
+pub struct Sizedness<T: ?Sized>(PhantomData<T>);
+
+#[allow(clippy::new_without_default, clippy::needless_return)]
+impl<T: ?Sized> Sizedness<T> {
+    pub const fn new() -> Self {
+        return Self(PhantomData);
+    }
+}
+
+/// **don't use. internal only, see `Sizedness`**
+#[doc(hidden)]
+pub trait SizedFlag {
+    const SIZED: bool;
+}
+/// **don't use. internal only, see `Sizedness`**
+#[doc(hidden)]
+pub struct IsSized;
+/// **don't use. internal only, see `Sizedness`**
+#[doc(hidden)]
+pub struct IsUnsized;
+impl SizedFlag for IsSized {
+    const SIZED: bool = true;
+}
+impl SizedFlag for IsUnsized {
+    const SIZED: bool = false;
+}
+
+/// **don't use. internal only, see `Sizedness`**
+///
+/// The closure is never called: we only want the type it returns,
+/// so this works in const context without calling trait methods
+#[doc(hidden)]
+#[allow(clippy::needless_return)]
+pub const fn is_sized<F: Fn() -> R, R: SizedFlag>(_probe: &F) -> bool {
+    return R::SIZED;
+}
+
+/// **don't use. internal only, see `Sizedness`**
+///
+/// Erase the entries that only sized types have
+#[doc(hidden)]
+pub trait ProbeSized<T: ?Sized> {
+    fn probe(&self) -> IsSized;
+    /// Erase as `dyn AnyTrait`
+    fn erase_anytrait(&self, ptr: *mut T) -> AnyPtr;
+    /// Erase as `dyn Any`
+    fn erase_any(&self, ptr: *mut T) -> AnyPtr;
+    /// Upcast to `dyn AnyTrait`
+    fn upcast<'a>(&self, obj: &'a T) -> &'a dyn AnyTrait;
+    /// Upcast to `dyn AnyTrait`
+    fn upcast_mut<'a>(&self, obj: &'a mut T) -> &'a mut dyn AnyTrait;
+}
+/// **don't use. internal only, see `Sizedness`**
+///
+/// Unsized types list themselves instead of `dyn AnyTrait` and `dyn Any`
+#[doc(hidden)]
+pub trait ProbeUnsized<T: ?Sized> {
+    fn probe(&self) -> IsUnsized;
+    /// Erase as the concrete type
+    fn erase_anytrait(&self, ptr: *mut T) -> AnyPtr;
+    /// Erase as the concrete type
+    fn erase_any(&self, ptr: *mut T) -> AnyPtr;
+    /// Panics: unsized types can't be a `dyn AnyTrait`
+    fn upcast<'a>(&self, obj: &'a T) -> &'a dyn AnyTrait;
+    /// Panics: unsized types can't be a `dyn AnyTrait`
+    fn upcast_mut<'a>(&self, obj: &'a mut T) -> &'a mut dyn AnyTrait;
+}
+
+#[allow(clippy::needless_return)]
+impl<T: AnyTrait> ProbeSized<T> for Sizedness<T> {
+    fn probe(&self) -> IsSized {
+        return IsSized;
+    }
+    fn erase_anytrait(&self, ptr: *mut T) -> AnyPtr {
+        return AnyPtr::from_mut::<dyn AnyTrait>(ptr as *mut dyn AnyTrait);
+    }
+    fn erase_any(&self, ptr: *mut T) -> AnyPtr {
+        return AnyPtr::from_mut::<dyn Any>(ptr as *mut dyn Any);
+    }
+    fn upcast<'a>(&self, obj: &'a T) -> &'a dyn AnyTrait {
+        return obj;
+    }
+    fn upcast_mut<'a>(&self, obj: &'a mut T) -> &'a mut dyn AnyTrait {
+        return obj;
+    }
+}
+#[allow(clippy::needless_return)]
+impl<T: ?Sized + Erasable + 'static> ProbeUnsized<T> for &Sizedness<T> {
+    fn probe(&self) -> IsUnsized {
+        return IsUnsized;
+    }
+    fn erase_anytrait(&self, ptr: *mut T) -> AnyPtr {
+        return AnyPtr::from_mut::<T>(ptr);
+    }
+    fn erase_any(&self, ptr: *mut T) -> AnyPtr {
+        return AnyPtr::from_mut::<T>(ptr);
+    }
+    fn upcast<'a>(&self, _obj: &'a T) -> &'a dyn AnyTrait {
+        panic!("AnyTrait: unsized types can't be upcast to dyn AnyTrait");
+    }
+    fn upcast_mut<'a>(&self, _obj: &'a mut T) -> &'a mut dyn AnyTrait {
+        panic!("AnyTrait: unsized types can't be upcast to dyn AnyTrait");
     }
 }
@@ -0,0 +1,209 @@
+//! Safe, typed wrappers around `AnyPtr`
+//!
+//! `AnyPtr` knows nothing about the type or the lifetime of what it points
+//! to, so turning it back into something usable is `unsafe`.\
+//! `AnyRef<'a>` and `AnyMut<'a>` also carry the `TypeIdConst` of the
+//! original type and the lifetime of the borrow, so they can be passed around
+//! (e.g. in your own dispatch tables) and downcast safely.
+
+use crate::{
+    anyptr::{AnyPtr, Erasable},
+    typeidconst::TypeIdConst,
+};
+use ::core::{marker::PhantomData, ptr::Pointee};
+
+/// Type-erased shared reference
+///
+/// Like `&'a dyn Any`, but it can point to a `dyn Trait` too
+#[derive(Copy, Clone, Debug)]
+pub struct AnyRef<'a> {
+    ptr: AnyPtr,
+    type_id: TypeIdConst,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a> AnyRef<'a> {
+    /// Type-erase a reference to a `Sized` type, a `dyn Trait`
+    /// or a slice-like type
+    pub fn new<T: ?Sized + Erasable + 'static>(r: &'a T) -> Self {
+        return Self {
+            ptr: AnyPtr::from::<T>(r as *const T),
+            type_id: TypeIdConst::of::<T>(),
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// Type-erase a reference to a slice, a `str`
+    /// or a struct ending with one of those
+    ///
+    /// Same as `new`, but only accepts slice-like types
+    pub fn new_slice<T: ?Sized + Pointee<Metadata = usize> + 'static>(
+        r: &'a T,
+    ) -> Self {
+        return Self {
+            ptr: AnyPtr::from_slice::<T>(r as *const T),
+            type_id: TypeIdConst::of::<T>(),
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// # Safety
+    /// `ptr` must have been created from a `&'a T`
+    /// where `TypeIdConst::of::<T>() == type_id`
+    #[allow(unsafe_code)]
+    pub(crate) unsafe fn from_raw(ptr: AnyPtr, type_id: TypeIdConst) -> Self {
+        #[cfg(any(feature = "checked", debug_assertions))]
+        ptr.assert_type(type_id);
+        return Self {
+            ptr,
+            type_id,
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// `TypeIdConst` of the type we were created from
+    pub fn type_id(&self) -> TypeIdConst {
+        return self.type_id;
+    }
+
+    /// Check if we were created from a `&T`
+    pub fn is<T: ?Sized + 'static>(&self) -> bool {
+        return self.type_id == TypeIdConst::of::<T>();
+    }
+
+    /// Get back the original reference, if `T` is the original type
+    pub fn downcast<T: ?Sized + 'static>(self) -> Option<&'a T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        #[allow(unsafe_code)]
+        unsafe {
+            return Some(self.ptr.to_ptr::<T>().as_ref());
+        }
+    }
+}
+
+/// Same as `AnyRef::new`: `T` can be `Sized`, a `dyn Trait` or slice-like
+#[allow(clippy::needless_return)]
+impl<'a, T: ?Sized + Erasable + 'static> From<&'a T> for AnyRef<'a> {
+    fn from(r: &'a T) -> Self {
+        return Self::new::<T>(r);
+    }
+}
+
+/// Type-erased mutable reference
+///
+/// Like `&'a mut dyn Any`, but it can point to a `dyn Trait` too
+#[derive(Debug)]
+pub struct AnyMut<'a> {
+    ptr: AnyPtr,
+    type_id: TypeIdConst,
+    _lifetime: PhantomData<&'a mut ()>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a> AnyMut<'a> {
+    /// Type-erase a mutable reference to a `Sized` type, a `dyn Trait`
+    /// or a slice-like type
+    pub fn new<T: ?Sized + Erasable + 'static>(r: &'a mut T) -> Self {
+        return Self {
+            ptr: AnyPtr::from_mut::<T>(r as *mut T),
+            type_id: TypeIdConst::of::<T>(),
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// Type-erase a mutable reference to a slice, a `str`
+    /// or a struct ending with one of those
+    ///
+    /// Same as `new`, but only accepts slice-like types
+    pub fn new_slice<T: ?Sized + Pointee<Metadata = usize> + 'static>(
+        r: &'a mut T,
+    ) -> Self {
+        return Self {
+            ptr: AnyPtr::from_slice_mut::<T>(r as *mut T),
+            type_id: TypeIdConst::of::<T>(),
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// # Safety
+    /// `ptr` must have been created from a `&'a mut T`
+    /// where `TypeIdConst::of::<T>() == type_id`
+    #[allow(unsafe_code)]
+    pub(crate) unsafe fn from_raw(ptr: AnyPtr, type_id: TypeIdConst) -> Self {
+        #[cfg(any(feature = "checked", debug_assertions))]
+        ptr.assert_type(type_id);
+        return Self {
+            ptr,
+            type_id,
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// `TypeIdConst` of the type we were created from
+    pub fn type_id(&self) -> TypeIdConst {
+        return self.type_id;
+    }
+
+    /// Check if we were created from a `&mut T`
+    pub fn is<T: ?Sized + 'static>(&self) -> bool {
+        return self.type_id == TypeIdConst::of::<T>();
+    }
+
+    /// Reborrow for a shorter lifetime, so that `self` can be used again
+    pub fn reborrow(&mut self) -> AnyMut<'_> {
+        return AnyMut {
+            ptr: self.ptr,
+            type_id: self.type_id,
+            _lifetime: PhantomData,
+        };
+    }
+
+    /// Borrow as a shared reference, if `T` is the original type
+    pub fn downcast_ref<T: ?Sized + 'static>(&self) -> Option<&T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        #[allow(unsafe_code)]
+        unsafe {
+            return Some(self.ptr.to_ptr::<T>().as_ref());
+        }
+    }
+
+    /// Borrow as a mutable reference, if `T` is the original type
+    pub fn downcast_mut<T: ?Sized + 'static>(&mut self) -> Option<&mut T> {
+        return self.reborrow().downcast::<T>();
+    }
+
+    /// Get back the original mutable reference, if `T` is the original type
+    pub fn downcast<T: ?Sized + 'static>(self) -> Option<&'a mut T> {
+        if !self.is::<T>() {
+            return None;
+        }
+        #[allow(unsafe_code)]
+        unsafe {
+            return Some(self.ptr.to_ptr::<T>().as_mut());
+        }
+    }
+}
+
+/// Same as `AnyMut::new`: `T` can be `Sized`, a `dyn Trait` or slice-like
+#[allow(clippy::needless_return)]
+impl<'a, T: ?Sized + Erasable + 'static> From<&'a mut T> for AnyMut<'a> {
+    fn from(r: &'a mut T) -> Self {
+        return Self::new::<T>(r);
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<'a> From<AnyMut<'a>> for AnyRef<'a> {
+    fn from(r: AnyMut<'a>) -> Self {
+        return AnyRef {
+            ptr: r.ptr,
+            type_id: r.type_id,
+            _lifetime: PhantomData,
+        };
+    }
+}
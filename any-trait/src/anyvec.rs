@@ -0,0 +1,364 @@
+//! Heterogeneous collection with per-trait indexes
+//!
+//! **Requires the `alloc` feature**
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, anyvec::AnyVec};
+//! trait Update {
+//!     fn update(&mut self);
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Update)]
+//! struct Player {
+//!     x: usize,
+//! }
+//! impl Update for Player {
+//!     fn update(&mut self) {
+//!         self.x += 1;
+//!     }
+//! }
+//! #[derive(AnySubTrait)]
+//! struct Wall {}
+//!
+//! let mut scene = AnyVec::new();
+//! let player = scene.insert(Player { x: 0 });
+//! scene.insert(Wall {});
+//!
+//! for u in scene.iter_mut::<dyn Update>() {
+//!     u.update();
+//! }
+//! assert_eq!(scene.get::<Player>(player).unwrap().x, 1);
+//! ```
+//!
+//! When an element is inserted we go through its `type_ids()` once and
+//! remember, for every type in the list, which elements can be cast to it.\
+//! Iterating over a type then only goes through the elements that can be
+//! cast to it, without searching their `type_ids()` again.
+//!
+//! Elements are iterated in slot order: removed slots are reused,
+//! so this is not always the insertion order.
+
+use crate::{
+    AnyTrait, AnyTraitCast,
+    typeidconst::{TypeIdConst, first_listed},
+};
+use ::alloc::{boxed::Box, vec::Vec};
+use ::core::marker::PhantomData;
+
+/// Stable reference to an element of an `AnyVec`
+///
+/// Still valid after other elements are inserted or removed.
+/// Once its element is removed, the handle will never point to anything else
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: usize,
+    generation: u32,
+}
+
+struct Slot {
+    /// incremented every time the slot is emptied
+    generation: u32,
+    value: Option<Box<dyn AnyTrait>>,
+}
+
+/// An element that can be cast to the indexed type
+#[derive(Copy, Clone)]
+struct Member {
+    slot: usize,
+    /// index of the type in the `type_ids()` of the element
+    trait_idx: usize,
+}
+
+/// All the elements that can be cast to `type_id`, ordered by slot
+struct TraitIndex {
+    type_id: TypeIdConst,
+    members: Vec<Member>,
+}
+
+/// Heterogeneous collection of `AnyTrait` objects, indexed by trait
+pub struct AnyVec {
+    slots: Vec<Slot>,
+    /// empty slots, ready to be reused
+    free: Vec<usize>,
+    indexes: Vec<TraitIndex>,
+    len: usize,
+}
+
+#[allow(clippy::needless_return)]
+impl AnyVec {
+    pub const fn new() -> Self {
+        return Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            indexes: Vec::new(),
+            len: 0,
+        };
+    }
+
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    fn index_of(&self, type_id: TypeIdConst) -> Option<&TraitIndex> {
+        return self.indexes.iter().find(|i| i.type_id == type_id);
+    }
+
+    /// Number of elements that can be cast to `D`
+    pub fn count<D: ?Sized + 'static>(&self) -> usize {
+        return match self.index_of(TypeIdConst::of::<D>()) {
+            Some(index) => index.members.len(),
+            None => 0,
+        };
+    }
+
+    /// Add an element
+    pub fn insert<T: AnyTrait>(&mut self, value: T) -> Handle {
+        return self.insert_box(Box::new(value));
+    }
+
+    /// Add an element that is already boxed
+    pub fn insert_box(&mut self, value: Box<dyn AnyTrait>) -> Handle {
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                self.slots.len() - 1
+            }
+        };
+        let type_ids = value.type_ids();
+        for (trait_idx, type_id) in type_ids.iter().enumerate() {
+            if !first_listed(type_ids, trait_idx) {
+                continue;
+            }
+            let member = Member { slot, trait_idx };
+            let pos = self.indexes.iter().position(|i| i.type_id == *type_id);
+            match pos {
+                Some(pos) => {
+                    let members = &mut self.indexes[pos].members;
+                    let at = members.partition_point(|m| m.slot < slot);
+                    members.insert(at, member);
+                }
+                None => self.indexes.push(TraitIndex {
+                    type_id: *type_id,
+                    members: ::alloc::vec![member],
+                }),
+            }
+        }
+        self.slots[slot].value = Some(value);
+        self.len += 1;
+        return Handle {
+            slot,
+            generation: self.slots[slot].generation,
+        };
+    }
+
+    fn slot(&self, handle: Handle) -> Option<&dyn AnyTrait> {
+        let slot = self.slots.get(handle.slot)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        return slot.value.as_deref();
+    }
+
+    fn slot_mut(&mut self, handle: Handle) -> Option<&mut dyn AnyTrait> {
+        let slot = self.slots.get_mut(handle.slot)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        return slot.value.as_deref_mut();
+    }
+
+    /// Remove an element, if the handle is still valid
+    pub fn remove(&mut self, handle: Handle) -> Option<Box<dyn AnyTrait>> {
+        self.slot(handle)?;
+        let slot = &mut self.slots[handle.slot];
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        let type_ids = value.type_ids();
+        for (trait_idx, type_id) in type_ids.iter().enumerate() {
+            if !first_listed(type_ids, trait_idx) {
+                continue;
+            }
+            let Some(index) =
+                self.indexes.iter_mut().find(|i| i.type_id == *type_id)
+            else {
+                continue;
+            };
+            let at = index.members.partition_point(|m| m.slot < handle.slot);
+            index.members.remove(at);
+        }
+        self.free.push(handle.slot);
+        self.len -= 1;
+        return Some(value);
+    }
+
+    /// Check if the handle still points to an element
+    pub fn contains(&self, handle: Handle) -> bool {
+        return self.slot(handle).is_some();
+    }
+
+    /// Get an element as `&dyn AnyTrait`
+    pub fn get_any(&self, handle: Handle) -> Option<&dyn AnyTrait> {
+        return self.slot(handle);
+    }
+
+    /// Get an element as `&mut dyn AnyTrait`
+    pub fn get_any_mut(&mut self, handle: Handle) -> Option<&mut dyn AnyTrait> {
+        return self.slot_mut(handle);
+    }
+
+    /// Get an element cast to `D`. `D` can be the concrete type
+    pub fn get<D: ?Sized + 'static>(&self, handle: Handle) -> Option<&D> {
+        return self.slot(handle)?.cast_ref::<D>();
+    }
+
+    /// Get an element cast to `D`. `D` can be the concrete type
+    pub fn get_mut<D: ?Sized + 'static>(
+        &mut self,
+        handle: Handle,
+    ) -> Option<&mut D> {
+        return self.slot_mut(handle)?.cast_mut::<D>();
+    }
+
+    fn members<D: ?Sized + 'static>(&self) -> &[Member] {
+        return match self.index_of(TypeIdConst::of::<D>()) {
+            Some(index) => &index.members,
+            None => &[],
+        };
+    }
+
+    /// Iterate over all the elements that can be cast to `D`
+    pub fn iter<D: ?Sized + 'static>(&self) -> Iter<'_, D> {
+        return Iter {
+            slots: &self.slots,
+            members: self.members::<D>().iter(),
+            _d: PhantomData,
+        };
+    }
+
+    /// Iterate over all the elements that can be cast to `D`, with
+    /// their handles
+    pub fn iter_handles<D: ?Sized + 'static>(
+        &self,
+    ) -> impl Iterator<Item = (Handle, &D)> {
+        let members = self.members::<D>().iter();
+        return members.zip(self.iter::<D>()).map(|(m, d)| {
+            let handle = Handle {
+                slot: m.slot,
+                generation: self.slots[m.slot].generation,
+            };
+            return (handle, d);
+        });
+    }
+
+    /// Iterate mutably over all the elements that can be cast to `D`
+    pub fn iter_mut<D: ?Sized + 'static>(&mut self) -> IterMut<'_, D> {
+        let members = match self
+            .indexes
+            .iter()
+            .find(|i| i.type_id == TypeIdConst::of::<D>())
+        {
+            Some(index) => index.members.as_slice(),
+            None => &[],
+        };
+        return IterMut {
+            slots: &mut self.slots,
+            offset: 0,
+            members: members.iter(),
+            _d: PhantomData,
+        };
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl Default for AnyVec {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Iterator returned by `AnyVec::iter`
+pub struct Iter<'a, D: ?Sized> {
+    slots: &'a [Slot],
+    members: ::core::slice::Iter<'a, Member>,
+    _d: PhantomData<&'a D>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, D: ?Sized + 'static> Iterator for Iter<'a, D> {
+    type Item = &'a D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.members.next()?;
+        let value = self.slots[m.slot]
+            .value
+            .as_deref()
+            .expect("AnyVec: index points to an empty slot");
+        return Some(
+            value
+                .erase_ref(m.trait_idx)
+                .downcast::<D>()
+                .expect("AnyVec: wrong trait index"),
+        );
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.members.size_hint();
+    }
+}
+
+impl<'a, D: ?Sized + 'static> ExactSizeIterator for Iter<'a, D> {}
+
+/// Iterator returned by `AnyVec::iter_mut`
+pub struct IterMut<'a, D: ?Sized> {
+    /// slots we did not return yet, starting from `offset`
+    slots: &'a mut [Slot],
+    offset: usize,
+    members: ::core::slice::Iter<'a, Member>,
+    _d: PhantomData<&'a mut D>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, D: ?Sized + 'static> Iterator for IterMut<'a, D> {
+    type Item = &'a mut D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let m = self.members.next()?;
+        // members are ordered by slot, so we can split the slots and never
+        // go back to the ones we already returned
+        let slots = ::core::mem::take(&mut self.slots);
+        let at = m
+            .slot
+            .checked_sub(self.offset)
+            .expect("AnyVec: index not ordered by slot");
+        let (slot, rest) = slots[at..]
+            .split_first_mut()
+            .expect("AnyVec: index out of bounds");
+        self.slots = rest;
+        self.offset = m.slot + 1;
+        let value = slot
+            .value
+            .as_deref_mut()
+            .expect("AnyVec: index points to an empty slot");
+        return Some(
+            value
+                .erase_mut(m.trait_idx)
+                .downcast::<D>()
+                .expect("AnyVec: wrong trait index"),
+        );
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.members.size_hint();
+    }
+}
+
+impl<'a, D: ?Sized + 'static> ExactSizeIterator for IterMut<'a, D> {}
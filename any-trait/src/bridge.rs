@@ -0,0 +1,135 @@
+//! Bridge between `core::any::Any` and `AnyTrait`
+//!
+//! **Requires the `alloc` feature**
+//!
+//! Derived types always list `dyn Any` (and `dyn Any + Send + Sync` with
+//! `#[any_send_sync]`), so an `AnyTrait` can be handed to code that
+//! expects a `dyn Any`.
+//!
+//! The other way around, `AnyBridge` recovers a `dyn AnyTrait` from a
+//! `dyn Any`, if the concrete type was registered.
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, AnyTraitCast, bridge::AnyBridge};
+//! use std::any::Any;
+//! trait Shape {
+//!     fn area(&self) -> u32;
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Shape)]
+//! struct Square {
+//!     side: u32,
+//! }
+//! impl Shape for Square {
+//!     fn area(&self) -> u32 {
+//!         self.side * self.side
+//!     }
+//! }
+//!
+//! let sq = Square { side: 3 };
+//! let any: &dyn Any = sq.cast_ref::<dyn Any>().unwrap();
+//!
+//! let mut bridge = AnyBridge::new();
+//! bridge.register::<Square>();
+//! let obj = bridge.recover(any).unwrap();
+//! assert_eq!(obj.cast_ref::<dyn Shape>().unwrap().area(), 9);
+//! ```
+
+use crate::AnyTrait;
+use ::alloc::{boxed::Box, vec::Vec};
+use ::core::any::{Any, TypeId};
+
+type RecoverBox = fn(Box<dyn Any>) -> Result<Box<dyn AnyTrait>, Box<dyn Any>>;
+
+struct Known {
+    type_id: TypeId,
+    recover: fn(&dyn Any) -> Option<&dyn AnyTrait>,
+    recover_mut: fn(&mut dyn Any) -> Option<&mut dyn AnyTrait>,
+    recover_box: RecoverBox,
+}
+
+#[allow(clippy::needless_return)]
+fn recover<T: AnyTrait>(any: &dyn Any) -> Option<&dyn AnyTrait> {
+    return any.downcast_ref::<T>().map(|t| t as &dyn AnyTrait);
+}
+
+#[allow(clippy::needless_return)]
+fn recover_mut<T: AnyTrait>(any: &mut dyn Any) -> Option<&mut dyn AnyTrait> {
+    return any.downcast_mut::<T>().map(|t| t as &mut dyn AnyTrait);
+}
+
+#[allow(clippy::needless_return)]
+fn recover_box<T: AnyTrait>(
+    any: Box<dyn Any>,
+) -> Result<Box<dyn AnyTrait>, Box<dyn Any>> {
+    return any.downcast::<T>().map(|t| t as Box<dyn AnyTrait>);
+}
+
+/// Registry of the concrete types we can recover from a `dyn Any`
+pub struct AnyBridge {
+    known: Vec<Known>,
+}
+
+#[allow(clippy::needless_return)]
+impl AnyBridge {
+    pub const fn new() -> Self {
+        return Self { known: Vec::new() };
+    }
+
+    /// Make `T` recoverable. Registering the same type twice does nothing
+    pub fn register<T: AnyTrait>(&mut self) {
+        if self.contains::<T>() {
+            return;
+        }
+        self.known.push(Known {
+            type_id: TypeId::of::<T>(),
+            recover: recover::<T>,
+            recover_mut: recover_mut::<T>,
+            recover_box: recover_box::<T>,
+        });
+    }
+
+    /// Check if `T` was registered
+    pub fn contains<T: AnyTrait>(&self) -> bool {
+        let type_id = TypeId::of::<T>();
+        return self.known.iter().any(|k| k.type_id == type_id);
+    }
+
+    fn find(&self, type_id: TypeId) -> Option<&Known> {
+        return self.known.iter().find(|k| k.type_id == type_id);
+    }
+
+    /// Get back the `dyn AnyTrait`, if the concrete type was registered
+    pub fn recover<'a>(&self, any: &'a dyn Any) -> Option<&'a dyn AnyTrait> {
+        let known = self.find(any.type_id())?;
+        return (known.recover)(any);
+    }
+
+    /// Get back the `dyn AnyTrait`, if the concrete type was registered
+    pub fn recover_mut<'a>(
+        &self,
+        any: &'a mut dyn Any,
+    ) -> Option<&'a mut dyn AnyTrait> {
+        let known = self.find((*any).type_id())?;
+        return (known.recover_mut)(any);
+    }
+
+    /// Get back the `dyn AnyTrait`, if the concrete type was registered.
+    /// Gives back the box otherwise
+    pub fn recover_box(
+        &self,
+        any: Box<dyn Any>,
+    ) -> Result<Box<dyn AnyTrait>, Box<dyn Any>> {
+        let Some(known) = self.find((*any).type_id()) else {
+            return Err(any);
+        };
+        return (known.recover_box)(any);
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl Default for AnyBridge {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
@@ -0,0 +1,127 @@
+//! Errors for failed casts
+//!
+//! `cast_ref`/`cast_mut` only tell you that the cast failed.\
+//! `try_cast_ref`/`try_cast_mut` tell you what you asked for, what the type
+//! actually is, and what it could have been cast to.
+
+use crate::typeidconst::TypeIdConst;
+
+/// Why a cast failed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CastError {
+    /// `core::any::type_name` of the type we tried to cast to
+    pub requested: &'static str,
+    /// `core::any::type_name` of the concrete type
+    pub concrete: &'static str,
+    /// names of all the types we can cast to. Same as
+    /// `AnyTrait::trait_names()`
+    pub available: &'static [&'static str],
+    /// Something in `available` looks a lot like what was requested
+    pub near_miss: Option<NearMiss>,
+}
+
+/// A type in the castable list that looks like the requested one,
+/// but is not the same type
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NearMiss {
+    /// `available[idx]` has the same name but a different `TypeId`.
+    ///
+    /// This happens when two semver-incompatible copies of a crate are
+    /// linked together
+    SameName { idx: usize },
+    /// `available[idx]` only differs in the auto traits.
+    ///
+    /// e.g: `dyn TA + Send` was requested, but only `dyn TA` is listed
+    ///
+    /// This is a guess based on `core::any::type_name`, whose output is
+    /// not guaranteed to be stable: we drop `Send`, `Sync` and `Unpin`
+    /// from the names and compare the rest. Other auto traits
+    /// (e.g. `UnwindSafe`) are not recognized
+    AutoTraits { idx: usize },
+}
+
+/// Auto traits as written by `core::any::type_name`
+const AUTO_TRAITS: [&str; 3] = [
+    "core::marker::Send",
+    "core::marker::Sync",
+    "core::marker::Unpin",
+];
+
+/// Compare two type names, ignoring the auto traits
+fn eq_without_auto_traits(a: &str, b: &str) -> bool {
+    let mut a_iter = a.split(" + ").filter(|t| !AUTO_TRAITS.contains(t));
+    let mut b_iter = b.split(" + ").filter(|t| !AUTO_TRAITS.contains(t));
+    loop {
+        match (a_iter.next(), b_iter.next()) {
+            (None, None) => return true,
+            (Some(a_part), Some(b_part)) if a_part == b_part => continue,
+            _ => return false,
+        }
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl CastError {
+    /// Build the error for a failed cast to `D`
+    ///
+    /// `type_ids` and `names` are the ones of the object we tried to cast
+    pub(crate) fn new<D: ?Sized + 'static>(
+        type_ids: &'static [TypeIdConst],
+        names: &'static [&'static str],
+    ) -> Self {
+        let requested = ::core::any::type_name::<D>();
+        let requested_id = TypeIdConst::of::<D>();
+
+        let mut near_miss = None;
+        for (idx, name) in names.iter().enumerate() {
+            if type_ids[idx] == requested_id {
+                continue;
+            }
+            if *name == requested {
+                near_miss = Some(NearMiss::SameName { idx });
+                break;
+            }
+            if near_miss.is_none() && eq_without_auto_traits(name, requested) {
+                // keep looking, `SameName` is more important
+                near_miss = Some(NearMiss::AutoTraits { idx });
+            }
+        }
+
+        return CastError {
+            requested,
+            concrete: names[1],
+            available: names,
+            near_miss,
+        };
+    }
+}
+
+impl ::core::fmt::Display for CastError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(f, "can't cast `{}` to `{}`", self.concrete, self.requested)?;
+        match self.near_miss {
+            None => {}
+            Some(NearMiss::SameName { idx }) => write!(
+                f,
+                ". `{}` is listed, but with a different TypeId: is the same \
+                 crate linked twice with different versions?",
+                self.available[idx]
+            )?,
+            Some(NearMiss::AutoTraits { idx }) => write!(
+                f,
+                ". Only `{}` is listed: auto traits must match exactly",
+                self.available[idx]
+            )?,
+        }
+        write!(f, ". available: ")?;
+        for (idx, name) in self.available.iter().enumerate() {
+            if idx != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "`{}`", name)?;
+        }
+        Ok(())
+    }
+}
+
+impl ::core::error::Error for CastError {}
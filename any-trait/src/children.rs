@@ -0,0 +1,221 @@
+//! Composite objects: walk an object and all its children
+//!
+//! Mark the fields that hold children with `#[any_children]`:
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, AnyTraitCast, children::Walk};
+//! trait Draw {
+//!     fn draw(&self) -> usize;
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Draw)]
+//! struct Button {}
+//! impl Draw for Button {
+//!     fn draw(&self) -> usize {
+//!         1
+//!     }
+//! }
+//! #[derive(AnySubTrait)]
+//! struct Panel {
+//!     #[any_children]
+//!     widgets: [Button; 2],
+//!     #[any_children]
+//!     close: Option<Button>,
+//! }
+//!
+//! let panel = Panel {
+//!     widgets: [Button {}, Button {}],
+//!     close: Some(Button {}),
+//! };
+//! let mut drawn = 0;
+//! panel.cast_all::<dyn Draw, _>(Walk::pre(), |d| drawn += d.draw());
+//! assert_eq!(drawn, 3);
+//! ```
+//!
+//! Supported fields are anything that implements `AnyChildren`:
+//! types that implement `AnyTrait`, `Box<dyn AnyTrait>`,
+//! `Box<dyn AnyTrait + Send + Sync>`, and `Vec`,
+//! arrays, slices and `Option` of those.
+
+use crate::{AnyTrait, AnyTraitCast};
+
+/// A field that holds children of an `AnyTrait` object
+///
+/// **Automatically implemented on `AnyTrait` types, `Box<dyn AnyTrait>`,
+/// `Box<dyn AnyTrait + Send + Sync>` and `Vec`, arrays, slices and `Option`
+/// of those**
+pub trait AnyChildren {
+    /// Call `f` on every child
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait));
+    /// Call `f` on every child
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait));
+}
+
+impl<T: AnyTrait> AnyChildren for T {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        f(self);
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        f(self);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AnyChildren for ::alloc::boxed::Box<dyn AnyTrait> {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        f(&**self);
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        f(&mut **self);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AnyChildren for ::alloc::boxed::Box<dyn AnyTrait + Send + Sync> {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        f(&**self);
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        f(&mut **self);
+    }
+}
+
+impl<T: AnyChildren> AnyChildren for [T] {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        for child in self.iter() {
+            child.for_each_child(f);
+        }
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        for child in self.iter_mut() {
+            child.for_each_child_mut(f);
+        }
+    }
+}
+
+impl<T: AnyChildren, const N: usize> AnyChildren for [T; N] {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        self.as_slice().for_each_child(f);
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        self.as_mut_slice().for_each_child_mut(f);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: AnyChildren> AnyChildren for ::alloc::vec::Vec<T> {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        self.as_slice().for_each_child(f);
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        self.as_mut_slice().for_each_child_mut(f);
+    }
+}
+
+impl<T: AnyChildren> AnyChildren for Option<T> {
+    fn for_each_child(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        if let Some(child) = self {
+            child.for_each_child(f);
+        }
+    }
+    fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        if let Some(child) = self {
+            child.for_each_child_mut(f);
+        }
+    }
+}
+
+/// When to visit an object, compared to its children
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Order {
+    /// The object first, then its children
+    Pre,
+    /// The children first, then the object
+    Post,
+}
+
+/// How to walk the children in `AnyTraitCast::cast_all`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Walk {
+    pub order: Order,
+    /// `0` only visits the object itself, `1` its direct children too...\
+    /// `None` means no limit
+    pub max_depth: Option<usize>,
+}
+
+#[allow(clippy::needless_return)]
+impl Walk {
+    /// Pre-order walk, no depth limit
+    pub const fn pre() -> Self {
+        return Self {
+            order: Order::Pre,
+            max_depth: None,
+        };
+    }
+    /// Post-order walk, no depth limit
+    pub const fn post() -> Self {
+        return Self {
+            order: Order::Post,
+            max_depth: None,
+        };
+    }
+    /// Don't go deeper than `max_depth` levels of children
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        return self;
+    }
+    /// Can we visit the children of an object at `depth`?
+    pub(crate) fn go_deeper(&self, depth: usize) -> bool {
+        return match self.max_depth {
+            Some(max_depth) => depth < max_depth,
+            None => true,
+        };
+    }
+}
+
+/// Visit `obj` and its children at `depth` and deeper
+pub(crate) fn walk<T: AnyTrait + ?Sized, D: ?Sized + 'static>(
+    obj: &T,
+    walk_opts: Walk,
+    depth: usize,
+    f: &mut dyn FnMut(&D),
+) {
+    if walk_opts.order == Order::Pre
+        && let Some(d) = obj.cast_ref::<D>()
+    {
+        f(d);
+    }
+    if walk_opts.go_deeper(depth) {
+        obj.children(&mut |child| {
+            walk::<dyn AnyTrait, D>(child, walk_opts, depth + 1, f)
+        });
+    }
+    if walk_opts.order == Order::Post
+        && let Some(d) = obj.cast_ref::<D>()
+    {
+        f(d);
+    }
+}
+
+/// Visit `obj` and its children at `depth` and deeper
+pub(crate) fn walk_mut<T: AnyTrait + ?Sized, D: ?Sized + 'static>(
+    obj: &mut T,
+    walk_opts: Walk,
+    depth: usize,
+    f: &mut dyn FnMut(&mut D),
+) {
+    if walk_opts.order == Order::Pre
+        && let Some(d) = obj.cast_mut::<D>()
+    {
+        f(d);
+    }
+    if walk_opts.go_deeper(depth) {
+        obj.children_mut(&mut |child| {
+            walk_mut::<dyn AnyTrait, D>(child, walk_opts, depth + 1, f)
+        });
+    }
+    if walk_opts.order == Order::Post
+        && let Some(d) = obj.cast_mut::<D>()
+    {
+        f(d);
+    }
+}
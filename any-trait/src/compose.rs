@@ -0,0 +1,225 @@
+//! Glue existing objects together into one `AnyTrait`
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, AnyTraitCast, compose::Compose};
+//! trait Position {
+//!     fn x(&self) -> i32;
+//! }
+//! trait Health {
+//!     fn hp(&self) -> u32;
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Position)]
+//! struct Body {}
+//! impl Position for Body {
+//!     fn x(&self) -> i32 {
+//!         3
+//!     }
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Health)]
+//! struct Life {}
+//! impl Health for Life {
+//!     fn hp(&self) -> u32 {
+//!         100
+//!     }
+//! }
+//!
+//! let both = Compose(Body {}, Life {});
+//! let any: &dyn AnyTrait = &both;
+//! assert_eq!(any.cast_ref::<dyn Position>().unwrap().x(), 3);
+//! assert_eq!(any.cast_ref::<dyn Health>().unwrap().hp(), 100);
+//! assert!(any.cast_ref::<Life>().is_some());
+//! ```
+//!
+//! `Compose<A, B>` and the tuples `(A, B)` up to `(A, B, C, D)` implement
+//! `AnyTrait`. Their `type_ids()` are:
+//! * `dyn AnyTrait`
+//! * the composed type itself
+//! * `dyn Any`, for the composed type
+//! * the `type_ids()` of every component, in order, except their first element
+//!   (`dyn AnyTrait`) and their `dyn Any` rows
+//!
+//! If two components can be cast to the same type, the leftmost wins.
+//!
+//! Note that `dyn Any` is at index 2 here, while derived types list it after
+//! their own traits: don't rely on its index, search for it.\
+//! The `dyn Any + Send + Sync` of the components (see `#[any_send_sync]`)
+//! are skipped: they would downcast to the component, not to the composed
+//! type, and we can't know if the composed type is `Send + Sync`.
+//!
+//! The components must implement `AnyTraitStatic`, so that the list can be
+//! built at compile time. It can't be longer than `MAX_TRAITS`.
+//!
+//! Composed types implement `AnyTraitStatic` too, so they can be nested:
+//! `Compose<Compose<A, B>, C>`.
+
+use crate::{
+    AnyTrait, AnyTraitStatic,
+    anyptr::AnyPtr,
+    typeidconst::{TypeIdConst, type_name},
+};
+use ::core::any::Any;
+
+/// Maximum length of the `type_ids()` of a composed type
+pub const MAX_TRAITS: usize = 32;
+
+/// Two objects glued together
+///
+/// see the `compose` module
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Compose<A, B>(pub A, pub B);
+
+/// `type_ids()` and `trait_names()` of a composed type,
+/// and where each type comes from
+struct Table {
+    type_ids: [TypeIdConst; MAX_TRAITS],
+    names: [&'static str; MAX_TRAITS],
+    /// `(component, index in the component type_ids())`
+    component: [(usize, usize); MAX_TRAITS],
+    len: usize,
+}
+
+#[allow(clippy::needless_return)]
+impl Table {
+    /// Build the table of `T`, composed of `parts`
+    ///
+    /// `parts` are `(TYPE_IDS, TRAIT_NAMES)` of each component
+    const fn new<T: 'static>(
+        parts: &[(&'static [TypeIdConst], &'static [&'static str])],
+    ) -> Self {
+        let mut table = Table {
+            type_ids: [TypeIdConst::of::<T>(); MAX_TRAITS],
+            names: [type_name::<T>(); MAX_TRAITS],
+            component: [(usize::MAX, 0); MAX_TRAITS],
+            len: 3,
+        };
+        table.type_ids[0] = TypeIdConst::of::<dyn AnyTrait>();
+        table.names[0] = type_name::<dyn AnyTrait>();
+        // the components are `dyn Any` too, but that is not the composed type
+        table.type_ids[2] = TypeIdConst::of::<dyn Any>();
+        table.names[2] = type_name::<dyn Any>();
+
+        let mut part: usize = 0;
+        while part < parts.len() {
+            let (type_ids, names) = parts[part];
+            // skip the `dyn AnyTrait` of each component
+            let mut idx: usize = 1;
+            while idx < type_ids.len() {
+                if !is_any(&type_ids[idx]) && !table.contains(&type_ids[idx]) {
+                    assert!(
+                        table.len < MAX_TRAITS,
+                        "Compose: too many traits, see MAX_TRAITS"
+                    );
+                    table.type_ids[table.len] = type_ids[idx];
+                    table.names[table.len] = names[idx];
+                    table.component[table.len] = (part, idx);
+                    table.len = table.len + 1;
+                }
+                idx = idx + 1;
+            }
+            part = part + 1;
+        }
+        return table;
+    }
+
+    const fn contains(&self, t: &TypeIdConst) -> bool {
+        let mut idx: usize = 0;
+        while idx < self.len {
+            if self.type_ids[idx].eq(t) {
+                return true;
+            }
+            idx = idx + 1;
+        }
+        return false;
+    }
+}
+
+/// `dyn Any` with any auto trait: they identify the concrete type,
+/// so they must never point to a component
+#[allow(clippy::needless_return)]
+const fn is_any(t: &TypeIdConst) -> bool {
+    return t.eq(&TypeIdConst::of::<dyn Any>())
+        || t.eq(&TypeIdConst::of::<dyn Any + Send>())
+        || t.eq(&TypeIdConst::of::<dyn Any + Sync>())
+        || t.eq(&TypeIdConst::of::<dyn Any + Send + Sync>());
+}
+
+/// Give the composed types a place for their `Table`
+trait Composed {
+    const TABLE: &'static Table;
+}
+
+macro_rules! impl_compose {
+    ([$($ty:tt)+] $($t:ident $n:tt),+) => {
+        impl<$($t: AnyTraitStatic),+> Composed for $($ty)+ {
+            const TABLE: &'static Table = &Table::new::<Self>(&[
+                $(($t::TYPE_IDS, $t::TRAIT_NAMES)),+
+            ]);
+        }
+
+        impl<$($t: AnyTraitStatic),+> AnyTraitStatic for $($ty)+ {
+            const TYPE_IDS: &'static [TypeIdConst] =
+                Self::TABLE.type_ids.split_at(Self::TABLE.len).0;
+            const TRAIT_NAMES: &'static [&'static str] =
+                Self::TABLE.names.split_at(Self::TABLE.len).0;
+        }
+
+        impl<$($t: AnyTraitStatic),+> AnyTrait for $($ty)+ {
+            fn type_ids(&self) -> &'static [TypeIdConst] {
+                return Self::TYPE_IDS;
+            }
+            fn trait_names(&self) -> &'static [&'static str] {
+                return Self::TRAIT_NAMES;
+            }
+            fn type_erase(&self, trait_num: usize) -> AnyPtr {
+                match trait_num {
+                    0 => {
+                        let ptr = self as *const dyn AnyTrait;
+                        return AnyPtr::from::<dyn AnyTrait>(ptr);
+                    }
+                    1 => return AnyPtr::from::<Self>(self as *const Self),
+                    2 => return AnyPtr::from::<dyn Any>(self as *const dyn Any),
+                    _ => {}
+                }
+                assert!(
+                    trait_num < Self::TABLE.len,
+                    "AnyTrait: forced cast to wrong type idx"
+                );
+                let (component, idx) = Self::TABLE.component[trait_num];
+                match component {
+                    $($n => return self.$n.type_erase(idx),)+
+                    _ => unreachable!("Compose: wrong component"),
+                }
+            }
+            fn type_erase_mut(&mut self, trait_num: usize) -> AnyPtr {
+                match trait_num {
+                    0 => {
+                        let ptr = self as *mut dyn AnyTrait;
+                        return AnyPtr::from_mut::<dyn AnyTrait>(ptr);
+                    }
+                    1 => return AnyPtr::from_mut::<Self>(self as *mut Self),
+                    2 => {
+                        let ptr = self as *mut dyn Any;
+                        return AnyPtr::from_mut::<dyn Any>(ptr);
+                    }
+                    _ => {}
+                }
+                assert!(
+                    trait_num < Self::TABLE.len,
+                    "AnyTrait: forced cast to wrong type idx"
+                );
+                let (component, idx) = Self::TABLE.component[trait_num];
+                match component {
+                    $($n => return self.$n.type_erase_mut(idx),)+
+                    _ => unreachable!("Compose: wrong component"),
+                }
+            }
+        }
+    };
+}
+
+impl_compose!([Compose<A, B>] A 0, B 1);
+impl_compose!([(A, B)] A 0, B 1);
+impl_compose!([(A, B, C)] A 0, B 1, C 2);
+impl_compose!([(A, B, C, D)] A 0, B 1, C 2, D 3);
@@ -0,0 +1,246 @@
+//! Resolve services by the traits they implement
+//!
+//! **Requires the `alloc` feature**
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, container::Container};
+//! trait Clock {
+//!     fn now(&self) -> u64;
+//! }
+//! trait Storage {
+//!     fn stamp(&self) -> u64;
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Clock)]
+//! struct SystemClock {}
+//! impl Clock for SystemClock {
+//!     fn now(&self) -> u64 {
+//!         42
+//!     }
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Storage)]
+//! struct Db {
+//!     created: u64,
+//! }
+//! impl Storage for Db {
+//!     fn stamp(&self) -> u64 {
+//!         self.created
+//!     }
+//! }
+//!
+//! let mut app = Container::new();
+//! app.register(SystemClock {});
+//! app.register_factory(|c| Db {
+//!     created: c.resolve::<dyn Clock>().unwrap().now(),
+//! });
+//! assert_eq!(app.resolve::<dyn Storage>().unwrap().stamp(), 42);
+//! ```
+//!
+//! Factories are called the first time one of their traits is resolved,
+//! and only once. A factory that (indirectly) resolves itself will panic.
+//!
+//! A child container (`Container::child`) first searches its own services,
+//! then its parent's: services in the child override the parent ones.\
+//! Registering two services for the same trait in the same container is
+//! fine, but resolving that trait is an error.
+
+use crate::{
+    AnyTrait, AnyTraitCast, AnyTraitStatic, anyref::AnyRef,
+    typeidconst::TypeIdConst,
+};
+use ::alloc::{boxed::Box, vec::Vec};
+use ::core::cell::OnceCell;
+
+/// Why a service could not be resolved
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    /// No service can be cast to the requested type,
+    /// in this container or its parents
+    Missing {
+        /// `core::any::type_name` of the requested type
+        requested: &'static str,
+    },
+    /// More than one service in the same container can be cast to the
+    /// requested type
+    Ambiguous {
+        /// `core::any::type_name` of the requested type
+        requested: &'static str,
+        /// `core::any::type_name` of the concrete type of all the candidates
+        candidates: Vec<&'static str>,
+    },
+}
+
+impl ::core::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self {
+            ResolveError::Missing { requested } => {
+                write!(f, "no service registered for `{}`", requested)
+            }
+            ResolveError::Ambiguous {
+                requested,
+                candidates,
+            } => {
+                write!(f, "more than one service for `{}`: ", requested)?;
+                for (idx, name) in candidates.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{}`", name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ::core::error::Error for ResolveError {}
+
+type Factory = Box<dyn Fn(&Container<'_>) -> Box<dyn AnyTrait>>;
+
+struct Service {
+    /// same as `type_ids()` of the instance, known before creating it
+    type_ids: &'static [TypeIdConst],
+    /// `core::any::type_name` of the concrete type
+    concrete: &'static str,
+    instance: OnceCell<Box<dyn AnyTrait>>,
+    factory: Option<Factory>,
+}
+
+#[allow(clippy::needless_return)]
+impl Service {
+    fn get(&self, container: &Container<'_>) -> &dyn AnyTrait {
+        let instance = self.instance.get_or_init(|| {
+            let factory = self
+                .factory
+                .as_ref()
+                .expect("Container: service without instance nor factory");
+            return factory(container);
+        });
+        return &**instance;
+    }
+}
+
+/// Service container, resolves services by trait
+pub struct Container<'p> {
+    parent: Option<&'p Container<'p>>,
+    services: Vec<Service>,
+}
+
+#[allow(clippy::needless_return)]
+impl Container<'static> {
+    pub const fn new() -> Self {
+        return Self {
+            parent: None,
+            services: Vec::new(),
+        };
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl Default for Container<'static> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<'p> Container<'p> {
+    /// New container that falls back to `self` for the services
+    /// it does not have
+    pub fn child(&self) -> Container<'_> {
+        return Container {
+            parent: Some(self),
+            services: Vec::new(),
+        };
+    }
+
+    /// Register an already built service
+    pub fn register<T: AnyTrait>(&mut self, service: T) {
+        self.register_box(Box::new(service));
+    }
+
+    /// Register an already built, boxed service
+    pub fn register_box(&mut self, service: Box<dyn AnyTrait>) {
+        let type_ids = service.type_ids();
+        let concrete = service.type_name();
+        self.services.push(Service {
+            type_ids,
+            concrete,
+            instance: OnceCell::from(service),
+            factory: None,
+        });
+    }
+
+    /// Register a service that will be built on first use.
+    ///
+    /// The factory gets the container the service was registered in,
+    /// to resolve its own dependencies
+    pub fn register_factory<T, F>(&mut self, factory: F)
+    where
+        T: AnyTraitStatic,
+        F: Fn(&Container<'_>) -> T + 'static,
+    {
+        let factory: Factory = Box::new(move |container: &Container<'_>| {
+            let service: Box<dyn AnyTrait> = Box::new(factory(container));
+            return service;
+        });
+        self.services.push(Service {
+            type_ids: T::TYPE_IDS,
+            concrete: ::core::any::type_name::<T>(),
+            instance: OnceCell::new(),
+            factory: Some(factory),
+        });
+    }
+
+    /// Find the only service that can be cast to `type_id`
+    fn resolve_erased(
+        &self,
+        type_id: TypeIdConst,
+        requested: &'static str,
+    ) -> Result<AnyRef<'_>, ResolveError> {
+        let mut candidates = self.services.iter().filter_map(|s| {
+            let trait_idx = s.type_ids.iter().position(|t| *t == type_id)?;
+            return Some((s, trait_idx));
+        });
+        let Some((service, trait_idx)) = candidates.next() else {
+            return match self.parent {
+                Some(parent) => parent.resolve_erased(type_id, requested),
+                None => Err(ResolveError::Missing { requested }),
+            };
+        };
+        if candidates.clone().next().is_some() {
+            let mut names = ::alloc::vec![service.concrete];
+            names.extend(candidates.map(|(s, _)| s.concrete));
+            return Err(ResolveError::Ambiguous {
+                requested,
+                candidates: names,
+            });
+        }
+        return Ok(service.get(self).erase_ref(trait_idx));
+    }
+
+    /// Get the only service that can be cast to `D`
+    pub fn resolve<D: ?Sized + 'static>(&self) -> Result<&D, ResolveError> {
+        let any = self.resolve_erased(
+            TypeIdConst::of::<D>(),
+            ::core::any::type_name::<D>(),
+        )?;
+        return Ok(any
+            .downcast::<D>()
+            .expect("Container: service has the wrong type list"));
+    }
+
+    /// Check if a service for `D` is registered,
+    /// in this container or its parents
+    pub fn contains<D: ?Sized + 'static>(&self) -> bool {
+        let type_id = TypeIdConst::of::<D>();
+        if self.services.iter().any(|s| s.type_ids.contains(&type_id)) {
+            return true;
+        }
+        return match self.parent {
+            Some(parent) => parent.contains::<D>(),
+            None => false,
+        };
+    }
+}
@@ -0,0 +1,338 @@
+//! Multiple dispatch on the concrete types of two objects
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, dispatch::DispatchArray};
+//! #[derive(AnySubTrait)]
+//! struct Circle {}
+//! #[derive(AnySubTrait)]
+//! struct Square {}
+//!
+//! let mut table = DispatchArray::<&'static str, 4>::new().symmetric(true);
+//! table.register::<Circle, Square>(|_c, _s| "circle-square").unwrap();
+//!
+//! let (c, s) = (Circle {}, Square {});
+//! let (a, b): (&dyn AnyTrait, &dyn AnyTrait) = (&s, &c);
+//! assert_eq!(table.dispatch(a, b), Some("circle-square"));
+//! ```
+//!
+//! Handlers are searched in this order:
+//! * exact match on the concrete types: `type_ids()[1]` of both objects
+//! * if `symmetric`, exact match with the objects swapped
+//! * handlers added with `register_fallback`, in registration order. These
+//!   match if the objects can be cast to the handler types, so they can work on
+//!   traits (e.g. `dyn Shape`)
+//!
+//! Handlers are kept in a plain list and the search is linear in the number
+//! of handlers: fallbacks must be tried in registration order anyway, and
+//! `DispatchArray` can't allocate a hash map.
+//!
+//! `DispatchArray` has a fixed capacity, takes only `fn` pointers
+//! (closures that don't capture anything) and does not allocate.\
+//! `DispatchTable` needs the `alloc` feature and takes any closure.
+
+use crate::{AnyTrait, AnyTraitCast, typeidconst::TypeIdConst};
+
+/// A handler in a dispatch table
+struct Entry<H> {
+    a: TypeIdConst,
+    b: TypeIdConst,
+    /// `a` and `b` are concrete types, matched only on `type_ids()[1]`
+    exact: bool,
+    handler: H,
+}
+
+#[allow(clippy::needless_return)]
+impl<H> Entry<H> {
+    fn matches(&self, a: &dyn AnyTrait, b: &dyn AnyTrait) -> bool {
+        if self.exact {
+            return a.type_ids()[1] == self.a && b.type_ids()[1] == self.b;
+        }
+        return a.type_ids().contains(&self.a)
+            && b.type_ids().contains(&self.b);
+    }
+}
+
+/// Find the handler for `(a, b)`
+///
+/// Returns the entry, and `true` if the objects must be swapped
+#[allow(clippy::needless_return)]
+fn find<'e, H: 'e, I>(
+    entries: I,
+    symmetric: bool,
+    a: &dyn AnyTrait,
+    b: &dyn AnyTrait,
+) -> Option<(&'e Entry<H>, bool)>
+where
+    I: Iterator<Item = &'e Entry<H>> + Clone,
+{
+    let exact = entries.clone().filter(|e| e.exact);
+    for e in exact.clone() {
+        if e.matches(a, b) {
+            return Some((e, false));
+        }
+    }
+    if symmetric {
+        for e in exact {
+            if e.matches(b, a) {
+                return Some((e, true));
+            }
+        }
+    }
+    for e in entries.filter(|e| !e.exact) {
+        if e.matches(a, b) {
+            return Some((e, false));
+        }
+        if symmetric && e.matches(b, a) {
+            return Some((e, true));
+        }
+    }
+    return None;
+}
+
+/// Cast `obj` to `T`. For exact entries we already know `T` is
+/// the concrete type, so there is nothing to search
+#[allow(clippy::needless_return)]
+fn get<T: ?Sized + 'static>(obj: &dyn AnyTrait, exact: bool) -> Option<&T> {
+    if exact {
+        return obj.erase_ref(1).downcast::<T>();
+    }
+    return obj.cast_ref::<T>();
+}
+
+/// The table is full
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TableFull;
+
+impl ::core::fmt::Display for TableFull {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        write!(f, "dispatch table is full")
+    }
+}
+
+impl ::core::error::Error for TableFull {}
+
+/// Calls a type-erased `fn(&A, &B) -> R`, see `call_fn`
+type CallFn<R> = fn(fn(), bool, &dyn AnyTrait, &dyn AnyTrait) -> Option<R>;
+
+/// Type-erased `fn(&A, &B) -> R`
+struct FnHandler<R> {
+    f: fn(),
+    call: CallFn<R>,
+}
+
+#[allow(clippy::needless_return)]
+fn call_fn<A: ?Sized + 'static, B: ?Sized + 'static, R>(
+    f: fn(),
+    exact: bool,
+    a: &dyn AnyTrait,
+    b: &dyn AnyTrait,
+) -> Option<R> {
+    let a = get::<A>(a, exact)?;
+    let b = get::<B>(b, exact)?;
+    // `f` was a `fn(&A, &B) -> R` before being stored, see `FnHandler::new`
+    #[allow(unsafe_code)]
+    let f = unsafe { ::core::mem::transmute::<fn(), fn(&A, &B) -> R>(f) };
+    return Some(f(a, b));
+}
+
+#[allow(clippy::needless_return)]
+impl<R> FnHandler<R> {
+    fn new<A: ?Sized + 'static, B: ?Sized + 'static>(
+        f: fn(&A, &B) -> R,
+    ) -> Self {
+        return Self {
+            // we only transmute it back to the same type in `call_fn`
+            #[allow(unsafe_code)]
+            f: unsafe { ::core::mem::transmute::<fn(&A, &B) -> R, fn()>(f) },
+            call: call_fn::<A, B, R>,
+        };
+    }
+}
+
+/// Fixed-capacity dispatch table, no allocations
+///
+/// Holds up to `N` handlers
+pub struct DispatchArray<R, const N: usize> {
+    entries: [Option<Entry<FnHandler<R>>>; N],
+    symmetric: bool,
+}
+
+#[allow(clippy::needless_return)]
+impl<R, const N: usize> DispatchArray<R, N> {
+    pub const fn new() -> Self {
+        return Self {
+            entries: [const { None }; N],
+            symmetric: false,
+        };
+    }
+
+    /// If `(a, b)` has no handler, try `(b, a)`
+    pub const fn symmetric(mut self, symmetric: bool) -> Self {
+        self.symmetric = symmetric;
+        return self;
+    }
+
+    fn add(&mut self, entry: Entry<FnHandler<R>>) -> Result<(), TableFull> {
+        let mut free = None;
+        for (idx, e) in self.entries.iter().enumerate() {
+            match e {
+                Some(e)
+                    if e.exact == entry.exact
+                        && e.a == entry.a
+                        && e.b == entry.b =>
+                {
+                    free = Some(idx);
+                    break;
+                }
+                None if free.is_none() => free = Some(idx),
+                _ => {}
+            }
+        }
+        let Some(idx) = free else {
+            return Err(TableFull);
+        };
+        self.entries[idx] = Some(entry);
+        return Ok(());
+    }
+
+    /// Add or replace the handler for the concrete types `A` and `B`
+    pub fn register<A: AnyTrait, B: AnyTrait>(
+        &mut self,
+        f: fn(&A, &B) -> R,
+    ) -> Result<(), TableFull> {
+        return self.add(Entry {
+            a: TypeIdConst::of::<A>(),
+            b: TypeIdConst::of::<B>(),
+            exact: true,
+            handler: FnHandler::new(f),
+        });
+    }
+
+    /// Add or replace a handler used when there is no exact match.
+    ///
+    /// `A` and `B` can be any type in the `type_ids()` list,
+    /// e.g. `dyn Shape`
+    pub fn register_fallback<A: ?Sized + 'static, B: ?Sized + 'static>(
+        &mut self,
+        f: fn(&A, &B) -> R,
+    ) -> Result<(), TableFull> {
+        return self.add(Entry {
+            a: TypeIdConst::of::<A>(),
+            b: TypeIdConst::of::<B>(),
+            exact: false,
+            handler: FnHandler::new(f),
+        });
+    }
+
+    /// Call the handler for the types of `a` and `b`, if there is one
+    pub fn dispatch(&self, a: &dyn AnyTrait, b: &dyn AnyTrait) -> Option<R> {
+        let entries = self.entries.iter().flatten();
+        let (e, swap) = find(entries, self.symmetric, a, b)?;
+        let (a, b) = if swap { (b, a) } else { (a, b) };
+        return (e.handler.call)(e.handler.f, e.exact, a, b);
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<R, const N: usize> Default for DispatchArray<R, N> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+#[cfg(feature = "alloc")]
+type BoxHandler<R> =
+    ::alloc::boxed::Box<dyn Fn(&dyn AnyTrait, &dyn AnyTrait) -> Option<R>>;
+
+/// Growable dispatch table, accepts any closure
+#[cfg(feature = "alloc")]
+pub struct DispatchTable<R> {
+    entries: ::alloc::vec::Vec<Entry<BoxHandler<R>>>,
+    symmetric: bool,
+}
+
+#[cfg(feature = "alloc")]
+#[allow(clippy::needless_return)]
+impl<R: 'static> DispatchTable<R> {
+    pub const fn new() -> Self {
+        return Self {
+            entries: ::alloc::vec::Vec::new(),
+            symmetric: false,
+        };
+    }
+
+    /// If `(a, b)` has no handler, try `(b, a)`
+    pub fn symmetric(mut self, symmetric: bool) -> Self {
+        self.symmetric = symmetric;
+        return self;
+    }
+
+    fn add(&mut self, entry: Entry<BoxHandler<R>>) {
+        let old = self.entries.iter_mut().find(|e| {
+            e.exact == entry.exact && e.a == entry.a && e.b == entry.b
+        });
+        match old {
+            Some(old) => *old = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    fn boxed<A, B, F>(exact: bool, f: F) -> BoxHandler<R>
+    where
+        A: ?Sized + 'static,
+        B: ?Sized + 'static,
+        F: Fn(&A, &B) -> R + 'static,
+    {
+        return ::alloc::boxed::Box::new(move |a, b| {
+            return Some(f(get::<A>(a, exact)?, get::<B>(b, exact)?));
+        });
+    }
+
+    /// Add or replace the handler for the concrete types `A` and `B`
+    pub fn register<A, B, F>(&mut self, f: F)
+    where
+        A: AnyTrait,
+        B: AnyTrait,
+        F: Fn(&A, &B) -> R + 'static,
+    {
+        self.add(Entry {
+            a: TypeIdConst::of::<A>(),
+            b: TypeIdConst::of::<B>(),
+            exact: true,
+            handler: Self::boxed(true, f),
+        });
+    }
+
+    /// Add or replace a handler used when there is no exact match.
+    ///
+    /// `A` and `B` can be any type in the `type_ids()` list,
+    /// e.g. `dyn Shape`
+    pub fn register_fallback<A, B, F>(&mut self, f: F)
+    where
+        A: ?Sized + 'static,
+        B: ?Sized + 'static,
+        F: Fn(&A, &B) -> R + 'static,
+    {
+        self.add(Entry {
+            a: TypeIdConst::of::<A>(),
+            b: TypeIdConst::of::<B>(),
+            exact: false,
+            handler: Self::boxed(false, f),
+        });
+    }
+
+    /// Call the handler for the types of `a` and `b`, if there is one
+    pub fn dispatch(&self, a: &dyn AnyTrait, b: &dyn AnyTrait) -> Option<R> {
+        let (e, swap) = find(self.entries.iter(), self.symmetric, a, b)?;
+        let (a, b) = if swap { (b, a) } else { (a, b) };
+        return (e.handler)(a, b);
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[allow(clippy::needless_return)]
+impl<R: 'static> Default for DispatchTable<R> {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
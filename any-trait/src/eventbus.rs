@@ -0,0 +1,223 @@
+//! Deliver events to the subscribers that implement a handler trait
+//!
+//! **Requires the `alloc` feature**
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, eventbus::EventBus};
+//! use std::{cell::Cell, rc::Rc};
+//! trait OnResize {
+//!     fn on_resize(&self, w: u32, h: u32);
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(OnResize)]
+//! struct Window {
+//!     width: Cell<u32>,
+//! }
+//! impl OnResize for Window {
+//!     fn on_resize(&self, w: u32, _h: u32) {
+//!         self.width.set(w);
+//!     }
+//! }
+//!
+//! let mut bus = EventBus::new();
+//! let win: Rc<dyn AnyTrait> = Rc::new(Window { width: Cell::new(0) });
+//! bus.subscribe_weak(&win);
+//!
+//! assert_eq!(bus.publish::<dyn OnResize, _>(|h| h.on_resize(640, 480)), 1);
+//! drop(win);
+//! assert_eq!(bus.publish::<dyn OnResize, _>(|h| h.on_resize(1, 1)), 0);
+//! ```
+//!
+//! When an object subscribes we go through its `type_ids()` once and add it
+//! to the route of every type in the list. Publishing to a type only goes
+//! through the subscribers in that route, without searching their
+//! `type_ids()` again.
+//!
+//! Weak subscriptions don't keep the object alive. Once the object is
+//! dropped it is not delivered anything, and it is removed from the routes
+//! the next time they are published to, or by `EventBus::prune`.
+
+use crate::{
+    AnyTrait, AnyTraitCast,
+    typeidconst::{TypeIdConst, first_listed},
+};
+use ::alloc::{
+    rc::{Rc, Weak},
+    vec::Vec,
+};
+
+/// Identifies a subscription, to unsubscribe
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+#[derive(Clone)]
+enum Listener {
+    Strong(Rc<dyn AnyTrait>),
+    Weak(Weak<dyn AnyTrait>),
+}
+
+#[allow(clippy::needless_return)]
+impl Listener {
+    fn is_alive(&self) -> bool {
+        return match self {
+            Listener::Strong(_) => true,
+            Listener::Weak(w) => w.strong_count() != 0,
+        };
+    }
+}
+
+struct Member {
+    id: Subscription,
+    /// index of the route type in the `type_ids()` of the listener
+    trait_idx: usize,
+    listener: Listener,
+}
+
+/// All the subscribers that can be cast to `type_id`,
+/// in subscription order
+struct Route {
+    type_id: TypeIdConst,
+    members: Vec<Member>,
+}
+
+/// Routes events to subscribers by trait
+pub struct EventBus {
+    routes: Vec<Route>,
+    next_id: u64,
+}
+
+#[allow(clippy::needless_return)]
+impl EventBus {
+    pub const fn new() -> Self {
+        return Self {
+            routes: Vec::new(),
+            next_id: 0,
+        };
+    }
+
+    fn add(
+        &mut self,
+        type_ids: &[TypeIdConst],
+        listener: Listener,
+    ) -> Subscription {
+        let id = Subscription(self.next_id);
+        self.next_id += 1;
+        for (trait_idx, type_id) in type_ids.iter().enumerate() {
+            if !first_listed(type_ids, trait_idx) {
+                continue;
+            }
+            let member = Member {
+                id,
+                trait_idx,
+                listener: listener.clone(),
+            };
+            match self.routes.iter_mut().find(|r| r.type_id == *type_id) {
+                Some(route) => route.members.push(member),
+                None => self.routes.push(Route {
+                    type_id: *type_id,
+                    members: ::alloc::vec![member],
+                }),
+            }
+        }
+        return id;
+    }
+
+    /// Subscribe `listener`, keeping it alive until it is unsubscribed
+    pub fn subscribe(&mut self, listener: Rc<dyn AnyTrait>) -> Subscription {
+        let type_ids = listener.type_ids();
+        return self.add(type_ids, Listener::Strong(listener));
+    }
+
+    /// Subscribe `listener` until it is dropped
+    pub fn subscribe_weak(
+        &mut self,
+        listener: &Rc<dyn AnyTrait>,
+    ) -> Subscription {
+        let type_ids = listener.type_ids();
+        return self.add(type_ids, Listener::Weak(Rc::downgrade(listener)));
+    }
+
+    /// Remove a subscription. Returns `false` if it was not there
+    pub fn unsubscribe(&mut self, id: Subscription) -> bool {
+        let mut found = false;
+        for route in self.routes.iter_mut() {
+            let len = route.members.len();
+            route.members.retain(|m| m.id != id);
+            found |= route.members.len() != len;
+        }
+        return found;
+    }
+
+    /// Remove the weak subscriptions whose objects were dropped
+    pub fn prune(&mut self) {
+        for route in self.routes.iter_mut() {
+            route.members.retain(|m| m.listener.is_alive());
+        }
+    }
+
+    /// Number of live subscribers that can be cast to `D`
+    pub fn count<D: ?Sized + 'static>(&self) -> usize {
+        let type_id = TypeIdConst::of::<D>();
+        let Some(route) = self.routes.iter().find(|r| r.type_id == type_id)
+        else {
+            return 0;
+        };
+        return route
+            .members
+            .iter()
+            .filter(|m| m.listener.is_alive())
+            .count();
+    }
+
+    /// Call `f` on every subscriber that can be cast to `D`,
+    /// in subscription order.
+    ///
+    /// Returns the number of subscribers the event was delivered to
+    pub fn publish<D, F>(&mut self, mut f: F) -> usize
+    where
+        D: ?Sized + 'static,
+        F: FnMut(&D),
+    {
+        let type_id = TypeIdConst::of::<D>();
+        let Some(route) = self.routes.iter_mut().find(|r| r.type_id == type_id)
+        else {
+            return 0;
+        };
+        let mut delivered = 0;
+        let mut dead = false;
+        for m in route.members.iter() {
+            // keep weak listeners alive while we use them
+            let upgraded;
+            let obj: &dyn AnyTrait = match &m.listener {
+                Listener::Strong(rc) => &**rc,
+                Listener::Weak(w) => match w.upgrade() {
+                    Some(rc) => {
+                        upgraded = rc;
+                        &*upgraded
+                    }
+                    None => {
+                        dead = true;
+                        continue;
+                    }
+                },
+            };
+            let handler = obj
+                .erase_ref(m.trait_idx)
+                .downcast::<D>()
+                .expect("EventBus: wrong trait index");
+            f(handler);
+            delivered += 1;
+        }
+        if dead {
+            route.members.retain(|m| m.listener.is_alive());
+        }
+        return delivered;
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl Default for EventBus {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
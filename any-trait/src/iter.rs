@@ -0,0 +1,357 @@
+//! Iterator adapters over collections of `AnyTrait` objects
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, iter::AnyTraitIter};
+//! trait Tick {}
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Tick)]
+//! struct Clock {}
+//! impl Tick for Clock {}
+//! #[derive(AnySubTrait)]
+//! struct Rock {}
+//!
+//! let all: [&dyn AnyTrait; 3] = [&Clock {}, &Rock {}, &Clock {}];
+//! assert_eq!(all.into_iter().filter_cast::<dyn Tick>().count(), 2);
+//! ```
+//!
+//! Each element still needs to be checked, but if consecutive elements
+//! have the same concrete type we reuse the index we found for the
+//! previous one instead of searching the `type_ids()` list again.
+//!
+//! If all elements have the same concrete type, use `cast_slice` or
+//! `cast_slice_mut`: the index is found at compile time.
+
+use crate::{
+    AnyTrait, AnyTraitCast, AnyTraitStatic, cast_idx, cast_idx_mut,
+    static_trait_idx, typeidconst::TypeIdConst,
+};
+use ::core::marker::PhantomData;
+
+/// A shared reference to an `AnyTrait` object
+///
+/// Implemented on `&dyn AnyTrait` and `&Box<dyn AnyTrait>`
+pub trait AnyTraitRef<'a>: Copy {
+    fn into_anytrait(self) -> &'a dyn AnyTrait;
+}
+
+/// A mutable reference to an `AnyTrait` object
+///
+/// Implemented on `&mut dyn AnyTrait` and `&mut Box<dyn AnyTrait>`
+pub trait AnyTraitMut<'a> {
+    fn into_anytrait_mut(self) -> &'a mut dyn AnyTrait;
+}
+
+#[allow(clippy::needless_return)]
+impl<'a> AnyTraitRef<'a> for &'a dyn AnyTrait {
+    fn into_anytrait(self) -> &'a dyn AnyTrait {
+        return self;
+    }
+}
+#[allow(clippy::needless_return)]
+impl<'a> AnyTraitMut<'a> for &'a mut dyn AnyTrait {
+    fn into_anytrait_mut(self) -> &'a mut dyn AnyTrait {
+        return self;
+    }
+}
+#[cfg(feature = "alloc")]
+#[allow(clippy::needless_return)]
+impl<'a> AnyTraitRef<'a> for &'a ::alloc::boxed::Box<dyn AnyTrait> {
+    fn into_anytrait(self) -> &'a dyn AnyTrait {
+        return &**self;
+    }
+}
+#[cfg(feature = "alloc")]
+#[allow(clippy::needless_return)]
+impl<'a> AnyTraitMut<'a> for &'a mut ::alloc::boxed::Box<dyn AnyTrait> {
+    fn into_anytrait_mut(self) -> &'a mut dyn AnyTrait {
+        return &mut **self;
+    }
+}
+
+/// Remember the index of a trait for the last concrete type we saw
+#[derive(Copy, Clone)]
+struct LastIdx {
+    /// `type_ids()[1]` of the last element
+    concrete: Option<TypeIdConst>,
+    /// index of the trait in the last element, if it had one
+    trait_idx: Option<usize>,
+}
+
+#[allow(clippy::needless_return)]
+impl LastIdx {
+    fn new() -> Self {
+        return Self {
+            concrete: None,
+            trait_idx: None,
+        };
+    }
+    /// Find `D` in `obj`, possibly reusing the last result
+    fn find<D: ?Sized + 'static>(
+        &mut self,
+        obj: &dyn AnyTrait,
+    ) -> Option<usize> {
+        let concrete = obj.type_ids()[1];
+        if self.concrete != Some(concrete) {
+            self.concrete = Some(concrete);
+            self.trait_idx = obj.trait_idx::<D>();
+        }
+        return self.trait_idx;
+    }
+}
+
+/// Iterator adapters for shared references to `AnyTrait` objects
+///
+/// **Automatically implemented on all iterators of `&dyn AnyTrait`
+/// and `&Box<dyn AnyTrait>`**
+pub trait AnyTraitIter<'a>: Iterator + Sized
+where
+    Self::Item: AnyTraitRef<'a>,
+{
+    /// Only keep the elements that can be cast to `D`, and cast them
+    fn filter_cast<D: ?Sized + 'static>(self) -> FilterCast<'a, Self, D>;
+
+    /// Split the elements in the ones that can be cast to `D`, already cast,
+    /// and the ones that can't
+    fn partition_cast<D, Yes, No>(self) -> (Yes, No)
+    where
+        D: ?Sized + 'static,
+        Yes: Default + Extend<&'a D>,
+        No: Default + Extend<Self::Item>;
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, I> AnyTraitIter<'a> for I
+where
+    I: Iterator,
+    I::Item: AnyTraitRef<'a>,
+{
+    fn filter_cast<D: ?Sized + 'static>(self) -> FilterCast<'a, Self, D> {
+        return FilterCast {
+            iter: self,
+            last: LastIdx::new(),
+            _d: PhantomData,
+        };
+    }
+
+    fn partition_cast<D, Yes, No>(self) -> (Yes, No)
+    where
+        D: ?Sized + 'static,
+        Yes: Default + Extend<&'a D>,
+        No: Default + Extend<Self::Item>,
+    {
+        let mut yes = Yes::default();
+        let mut no = No::default();
+        let mut last = LastIdx::new();
+        for item in self {
+            let obj = item.into_anytrait();
+            match last.find::<D>(obj) {
+                Some(trait_idx) => {
+                    let d = obj.erase_ref(trait_idx).downcast::<D>().unwrap();
+                    yes.extend(::core::iter::once(d));
+                }
+                None => no.extend(::core::iter::once(item)),
+            }
+        }
+        return (yes, no);
+    }
+}
+
+/// Iterator adapters for mutable references to `AnyTrait` objects
+///
+/// **Automatically implemented on all iterators of `&mut dyn AnyTrait`
+/// and `&mut Box<dyn AnyTrait>`**
+pub trait AnyTraitIterMut<'a>: Iterator + Sized
+where
+    Self::Item: AnyTraitMut<'a>,
+{
+    /// Only keep the elements that can be cast to `D`, and cast them
+    fn filter_cast_mut<D: ?Sized + 'static>(self)
+    -> FilterCastMut<'a, Self, D>;
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, I> AnyTraitIterMut<'a> for I
+where
+    I: Iterator,
+    I::Item: AnyTraitMut<'a>,
+{
+    fn filter_cast_mut<D: ?Sized + 'static>(
+        self,
+    ) -> FilterCastMut<'a, Self, D> {
+        return FilterCastMut {
+            iter: self,
+            last: LastIdx::new(),
+            _d: PhantomData,
+        };
+    }
+}
+
+/// Iterator returned by `AnyTraitIter::filter_cast`
+pub struct FilterCast<'a, I, D: ?Sized> {
+    iter: I,
+    last: LastIdx,
+    _d: PhantomData<&'a D>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, I, D> Iterator for FilterCast<'a, I, D>
+where
+    I: Iterator,
+    I::Item: AnyTraitRef<'a>,
+    D: ?Sized + 'static,
+{
+    type Item = &'a D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.iter.by_ref() {
+            let obj = item.into_anytrait();
+            if let Some(trait_idx) = self.last.find::<D>(obj) {
+                return obj.erase_ref(trait_idx).downcast::<D>();
+            }
+        }
+        return None;
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (0, self.iter.size_hint().1);
+    }
+}
+
+/// Iterator returned by `AnyTraitIterMut::filter_cast_mut`
+pub struct FilterCastMut<'a, I, D: ?Sized> {
+    iter: I,
+    last: LastIdx,
+    _d: PhantomData<&'a mut D>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, I, D> Iterator for FilterCastMut<'a, I, D>
+where
+    I: Iterator,
+    I::Item: AnyTraitMut<'a>,
+    D: ?Sized + 'static,
+{
+    type Item = &'a mut D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for item in self.iter.by_ref() {
+            let obj = item.into_anytrait_mut();
+            if let Some(trait_idx) = self.last.find::<D>(obj) {
+                return obj.erase_mut(trait_idx).downcast::<D>();
+            }
+        }
+        return None;
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (0, self.iter.size_hint().1);
+    }
+}
+
+/// Cast every element of a slice of concrete types to `D`
+///
+/// The index of `D` is found at compile time from `T::TYPE_IDS`,
+/// so the elements are never checked.\
+/// Returns `None` if `T` can not be cast to `D`, even if the slice is empty.
+#[allow(clippy::needless_return)]
+pub fn cast_slice<T, D>(slice: &[T]) -> Option<CastSlice<'_, T, D>>
+where
+    T: AnyTraitStatic,
+    D: ?Sized + 'static,
+{
+    let trait_idx = const { static_trait_idx::<T, D>() }?;
+    return Some(CastSlice {
+        iter: slice.iter(),
+        trait_idx,
+        _d: PhantomData,
+    });
+}
+
+/// Cast every element of a mutable slice of concrete types to `D`
+///
+/// Same as `cast_slice`, but mutable
+#[allow(clippy::needless_return)]
+pub fn cast_slice_mut<T, D>(slice: &mut [T]) -> Option<CastSliceMut<'_, T, D>>
+where
+    T: AnyTraitStatic,
+    D: ?Sized + 'static,
+{
+    let trait_idx = const { static_trait_idx::<T, D>() }?;
+    return Some(CastSliceMut {
+        iter: slice.iter_mut(),
+        trait_idx,
+        _d: PhantomData,
+    });
+}
+
+/// Iterator returned by `cast_slice`
+pub struct CastSlice<'a, T, D: ?Sized> {
+    iter: ::core::slice::Iter<'a, T>,
+    trait_idx: usize,
+    _d: PhantomData<&'a D>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, T, D> Iterator for CastSlice<'a, T, D>
+where
+    T: AnyTraitStatic,
+    D: ?Sized + 'static,
+{
+    type Item = &'a D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let elem = self.iter.next()?;
+        // SAFETY: `trait_idx` is the index of `D` in `T::TYPE_IDS`
+        #[allow(unsafe_code)]
+        unsafe {
+            return Some(cast_idx::<T, D>(elem, self.trait_idx));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.iter.size_hint();
+    }
+}
+
+impl<'a, T, D> ExactSizeIterator for CastSlice<'a, T, D>
+where
+    T: AnyTraitStatic,
+    D: ?Sized + 'static,
+{
+}
+
+/// Iterator returned by `cast_slice_mut`
+pub struct CastSliceMut<'a, T, D: ?Sized> {
+    iter: ::core::slice::IterMut<'a, T>,
+    trait_idx: usize,
+    _d: PhantomData<&'a mut D>,
+}
+
+#[allow(clippy::needless_return)]
+impl<'a, T, D> Iterator for CastSliceMut<'a, T, D>
+where
+    T: AnyTraitStatic,
+    D: ?Sized + 'static,
+{
+    type Item = &'a mut D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let elem = self.iter.next()?;
+        // SAFETY: `trait_idx` is the index of `D` in `T::TYPE_IDS`
+        #[allow(unsafe_code)]
+        unsafe {
+            return Some(cast_idx_mut::<T, D>(elem, self.trait_idx));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return self.iter.size_hint();
+    }
+}
+
+impl<'a, T, D> ExactSizeIterator for CastSliceMut<'a, T, D>
+where
+    T: AnyTraitStatic,
+    D: ?Sized + 'static,
+{
+}
@@ -1,5 +1,11 @@
 #![feature(const_trait_impl)]
 #![feature(const_cmp)]
+// `Pointee` tells slices from `dyn Trait` in the type system: both `AnyPtr`
+// backends need it, the cargo feature only picks how pointers are split
+#![feature(ptr_metadata)]
+// the derive builds its const table of names with `type_name`
+#![feature(const_type_name)]
+#![feature(unsize)]
 #![no_std]
 
 //! # AnyTrait
@@ -7,6 +13,7 @@
 //! This is a **no_std** crate that lets you cast from:
 //! * your concrete type
 //! * `&dyn AnyTrait`
+//!
 //! to:
 //! * the concrete type
 //! * any other trait implemented by your type
@@ -49,13 +56,51 @@
 //!     let c_ref : &Concrete = a2.cast_ref::<Concrete>().unwrap();
 //! }
 //! ```
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+pub mod adapt;
+#[cfg(feature = "alloc")]
+pub mod anymap;
 pub mod anyptr;
+pub mod anyref;
+#[cfg(feature = "alloc")]
+pub mod anyvec;
+#[cfg(feature = "alloc")]
+pub mod bridge;
+pub mod casterror;
+pub mod children;
+pub mod compose;
+#[cfg(feature = "alloc")]
+pub mod container;
+pub mod dispatch;
+#[cfg(feature = "alloc")]
+pub mod eventbus;
+pub mod iter;
+pub mod matchcast;
+pub mod traitlist;
 pub mod typeidconst;
+pub mod vtable;
 
 use anyptr::AnyPtr;
+use anyref::{AnyMut, AnyRef};
+use casterror::CastError;
+use children::Walk;
+use core::marker::Unsize;
+use traitlist::TraitList;
 use typeidconst::TypeIdConst;
 
 pub use ::any_trait_macro::AnySubTrait;
+pub use vtable::{recover, recover_mut};
+
+/// Iterator over the `(TypeIdConst, type name)` of a type.
+///
+/// see `AnyTrait::traits()`
+pub type TraitsIter = ::core::iter::Zip<
+    ::core::iter::Copied<::core::slice::Iter<'static, TypeIdConst>>,
+    ::core::iter::Copied<::core::slice::Iter<'static, &'static str>>,
+>;
 
 /// # AnyTrait
 ///
@@ -74,6 +119,7 @@ pub use ::any_trait_macro::AnySubTrait;
 ///
 /// *`AnyTrait` is not necessarily fast as it needs check and track
 /// the list of traits you are allowed to cast to.*
+#[allow(clippy::needless_return)]
 pub trait AnyTrait: 'static {
     /// returns a list of all possible traits that you can up/downcast to\
     /// This list always has at least two elements:
@@ -82,8 +128,54 @@ pub trait AnyTrait: 'static {
     ///
     /// The reset of the list is currently unordered, will change as soon
     /// as we find a way to have a `const Ord` on `TypeId`
+    ///
+    /// Unsized types (e.g. ending with `[u8]`) can't be erased to
+    /// `dyn AnyTrait` or `dyn Any`: for them those rows are the concrete
+    /// type again
+    ///
+    /// `?Sized` type parameters are rejected by the derive:
+    /// ```compile_fail
+    /// use any_trait::{AnySubTrait, AnyTrait};
+    /// #[derive(AnySubTrait)]
+    /// struct Tail<T: ?Sized> {
+    ///     tail: T,
+    /// }
+    /// ```
     fn type_ids(&self) -> &'static [TypeIdConst];
 
+    /// returns the `core::any::type_name` of all the types in `.type_ids()`,
+    /// in the same order
+    ///
+    /// The derive implements this. By default every name is `"<unknown>"`
+    ///
+    /// # Panics
+    /// The default can't name more than 64 types: implement this yourself
+    /// if `.type_ids()` is longer
+    fn trait_names(&self) -> &'static [&'static str] {
+        const UNKNOWN: [&str; 64] = ["<unknown>"; 64];
+        let len = self.type_ids().len();
+        assert!(
+            len <= UNKNOWN.len(),
+            "AnyTrait: default trait_names supports at most 64 types"
+        );
+        return &UNKNOWN[..len];
+    }
+
+    /// `core::any::type_name` of the concrete type
+    fn type_name(&self) -> &'static str {
+        return self.trait_names()[1];
+    }
+
+    /// Iterate over the `TypeIdConst` and the name of all the types in
+    /// `.type_ids()`
+    fn traits(&self) -> TraitsIter {
+        return self
+            .type_ids()
+            .iter()
+            .copied()
+            .zip(self.trait_names().iter().copied());
+    }
+
     /// **don't use. internal only.**
     ///
     /// cast `self` to a trait in the `.type_ids()` list.\
@@ -112,6 +204,56 @@ pub trait AnyTrait: 'static {
     /// # Panics
     /// If list `trait_num` exceeds `type_ids()` length
     fn type_erase_mut(&mut self, trait_num: usize) -> AnyPtr;
+
+    /// Call `f` on every child object.
+    ///
+    /// The derive implements this for the fields marked `#[any_children]`.
+    /// By default there are no children
+    fn children(&self, f: &mut dyn FnMut(&dyn AnyTrait)) {
+        let _ = f;
+    }
+    /// Call `f` on every child object.
+    ///
+    /// The derive implements this for the fields marked `#[any_children]`.
+    /// By default there are no children
+    fn children_mut(&mut self, f: &mut dyn FnMut(&mut dyn AnyTrait)) {
+        let _ = f;
+    }
+
+    /// **don't use. internal only, see `AsAnyTrait`**
+    ///
+    /// The derive upcasts natively. By default we go through
+    /// `type_erase(0)`, which must be `dyn AnyTrait`
+    ///
+    /// # Panics
+    /// If `type_ids()[0]` is not `dyn AnyTrait`, like for unsized types
+    #[doc(hidden)]
+    #[allow(unsafe_code, clippy::needless_return)]
+    fn upcast_anytrait(&self) -> &dyn AnyTrait {
+        assert!(
+            self.type_ids()[0] == TypeIdConst::of::<dyn AnyTrait>(),
+            "AnyTrait: can't upcast to dyn AnyTrait"
+        );
+        // SAFETY: we just checked the type of the first row
+        return unsafe { cast_idx::<Self, dyn AnyTrait>(self, 0) };
+    }
+    /// **don't use. internal only, see `AsAnyTrait`**
+    ///
+    /// The derive upcasts natively. By default we go through
+    /// `type_erase_mut(0)`, which must be `dyn AnyTrait`
+    ///
+    /// # Panics
+    /// If `type_ids()[0]` is not `dyn AnyTrait`, like for unsized types
+    #[doc(hidden)]
+    #[allow(unsafe_code, clippy::needless_return)]
+    fn upcast_anytrait_mut(&mut self) -> &mut dyn AnyTrait {
+        assert!(
+            self.type_ids()[0] == TypeIdConst::of::<dyn AnyTrait>(),
+            "AnyTrait: can't upcast to dyn AnyTrait"
+        );
+        // SAFETY: we just checked the type of the first row
+        return unsafe { cast_idx_mut::<Self, dyn AnyTrait>(self, 0) };
+    }
 }
 
 /// upcast from the concrete type
@@ -119,11 +261,54 @@ pub trait AnyTrait: 'static {
 /// (or from any other trait that implements `AnyTrait`) to `&dyn AnyTrait`
 ///
 /// **Automatically implemented on all types that implement `AnyTrait`**
+///
+/// # Panics
+/// Unsized types can't be made into a `dyn AnyTrait`
 pub trait AsAnyTrait: AnyTrait {
     fn as_anytrait(&self) -> &dyn AnyTrait;
     fn as_anytrait_mut(&mut self) -> &mut dyn AnyTrait;
 }
 
+/// `dyn AnyTrait` that can be sent to other threads
+pub type AnyTraitSend = dyn AnyTrait + Send;
+
+/// upcast to `&AnyTraitSend`, keeping `Send`
+///
+/// For types that are `Send` but not `Sync`, like a `Box` moved to another
+/// thread. See `AsAnyTraitSync` for casting back to `dyn TA + Send`
+///
+/// **Automatically implemented on all sized types that implement `AnyTrait`
+/// and are `Send`, and on `dyn Trait + Send` when `Trait: AnyTrait`**
+pub trait AsAnyTraitSend: AnyTrait + Send {
+    fn as_anytrait_send(&self) -> &AnyTraitSend;
+    fn as_anytrait_send_mut(&mut self) -> &mut AnyTraitSend;
+    #[cfg(feature = "alloc")]
+    fn into_anytrait_send(
+        self: ::alloc::boxed::Box<Self>,
+    ) -> ::alloc::boxed::Box<AnyTraitSend>;
+}
+
+/// `dyn AnyTrait` that can be sent to and shared with other threads
+pub type AnyTraitSync = dyn AnyTrait + Send + Sync;
+
+/// upcast to `&AnyTraitSync`, keeping `Send` and `Sync`
+///
+/// `cast_ref` works as usual on the result. To get a `dyn TA + Send + Sync`
+/// back it must be listed as such: the derive lists them only with the
+/// opt-in `#[any_send_sync]`
+///
+/// **Automatically implemented on all sized types that implement `AnyTrait`
+/// and are `Send + Sync`, and on `dyn Trait + Send + Sync`
+/// when `Trait: AnyTrait`**
+pub trait AsAnyTraitSync: AnyTrait + Send + Sync {
+    fn as_anytrait_sync(&self) -> &AnyTraitSync;
+    fn as_anytrait_sync_mut(&mut self) -> &mut AnyTraitSync;
+    #[cfg(feature = "alloc")]
+    fn into_anytrait_sync(
+        self: ::alloc::boxed::Box<Self>,
+    ) -> ::alloc::boxed::Box<AnyTraitSync>;
+}
+
 /// (Up/Down)cast to another type
 ///
 /// **Automatically implemented on all types that implement `AnyTrait`**
@@ -133,6 +318,16 @@ pub trait AsAnyTrait: AnyTrait {
 pub trait AnyTraitCast: AnyTrait {
     /// Find the type in the supported trait list
     fn trait_idx<T: ?Sized + 'static>(&self) -> Option<usize>;
+    /// Check if we can cast to `D`, without actually casting
+    fn implements<D: ?Sized + 'static>(&self) -> bool;
+    /// Check if we can cast to all the types in the list
+    ///
+    /// example: `obj.implements_all::<(&dyn TA, &dyn TB)>()`
+    fn implements_all<L: TraitList>(&self) -> bool;
+    /// Check if we can cast to at least one of the types in the list
+    ///
+    /// example: `obj.implements_any::<(&dyn TA, &dyn TB)>()`
+    fn implements_any<L: TraitList>(&self) -> bool;
     /// (Up/Down)cast to a ref if the type is supported.
     ///
     /// Both Upcast and Downcast work, as long as the type is supported
@@ -141,34 +336,279 @@ pub trait AnyTraitCast: AnyTrait {
     ///
     /// Both Upcast and Downcast work, as long as the type is supported
     fn cast_mut<D: ?Sized + 'static>(&mut self) -> Option<&mut D>;
+    /// Cast to all the types in the list at once,
+    /// with a single search in the list of supported types
+    ///
+    /// example: `obj.cast_many::<(&dyn TA, &dyn TB)>()`
+    /// returns `Option<(&dyn TA, &dyn TB)>`
+    fn cast_many<L: TraitList>(&self) -> Option<L::Refs<'_>>;
+    /// Like `cast_ref`, but explain why the cast failed
+    fn try_cast_ref<D: ?Sized + 'static>(&self) -> Result<&D, CastError>;
+    /// Like `cast_mut`, but explain why the cast failed
+    fn try_cast_mut<D: ?Sized + 'static>(
+        &mut self,
+    ) -> Result<&mut D, CastError>;
+    /// Safely type-erase `self` as the `trait_num` type
+    /// in the `.type_ids()` list
+    ///
+    /// # Panics
+    /// If list `trait_num` exceeds `type_ids()` length
+    fn erase_ref(&self, trait_num: usize) -> AnyRef<'_>;
+    /// Safely type-erase `self` as the `trait_num` type
+    /// in the `.type_ids()` list
+    ///
+    /// # Panics
+    /// If list `trait_num` exceeds `type_ids()` length
+    fn erase_mut(&mut self, trait_num: usize) -> AnyMut<'_>;
+    /// Call `f` on `self` and on all its children (recursively)
+    /// that can be cast to `D`
+    ///
+    /// example: `panel.cast_all::<dyn Draw, _>(Walk::pre(), |d| d.draw())`
+    fn cast_all<D, F>(&self, walk: Walk, f: F)
+    where
+        D: ?Sized + 'static,
+        F: FnMut(&D);
+    /// Call `f` on `self` and on all its children (recursively)
+    /// that can be cast to `D`
+    fn cast_all_mut<D, F>(&mut self, walk: Walk, f: F)
+    where
+        D: ?Sized + 'static,
+        F: FnMut(&mut D);
 }
 
-// everybody can have the same implementation as the `dyn Any` is always
-// the first type in the list
-impl<T: AnyTrait + ?Sized> AsAnyTrait for T {
-    /// upcast to `&dyn AnyTrait`
+/// Compile-time list of the types we can cast to
+///
+/// **Don't implement manually**, `#[derive(AnySubTrait)]` does it for you.
+///
+/// If you have the concrete type you don't need to search `.type_ids()`
+/// at runtime: the cast can be resolved at compile time.\
+/// The derive returns `TYPE_IDS` from `.type_ids()`, so even `cast_ref` on
+/// a concrete type searches a constant list, and the optimizer can fold it.
+/// `static_cast` does the search in const context, so it never happens
+/// at runtime.
+///
+/// To check what a type can be cast to at compile time, use `implements`:
+/// `const OK: bool = any_trait::implements::<Concrete, dyn TA>();`
+///
+/// ```compile_fail
+/// use any_trait::{AnySubTrait, AnyTrait, AnyTraitStatic};
+/// trait TA {}
+/// #[derive(AnySubTrait)]
+/// struct Concrete {}
+/// impl TA for Concrete {}
+///
+/// let c = Concrete {};
+/// // `TA` is not in `#[any_sub_trait(..)]`: does not compile
+/// let ta: &dyn TA = c.static_cast::<dyn TA>();
+/// ```
+#[allow(clippy::needless_return, clippy::question_mark)]
+pub trait AnyTraitStatic: AnyTrait {
+    /// Same as `AnyTrait::type_ids()`, but usable in const context
+    const TYPE_IDS: &'static [TypeIdConst];
+    /// Same as `AnyTrait::trait_names()`, but usable in const context
+    const TRAIT_NAMES: &'static [&'static str];
+
+    /// (Up/Down)cast to a ref, or fail to compile if the type
+    /// is not supported
     #[inline]
-    fn as_anytrait(&self) -> &dyn AnyTrait {
-        let erased = self.type_erase(0);
+    fn static_cast<D: ?Sized + 'static>(&self) -> &D {
+        let trait_idx = const {
+            match static_trait_idx::<Self, D>() {
+                Some(trait_idx) => trait_idx,
+                None => panic!("AnyTraitStatic: static_cast to unlisted type"),
+            }
+        };
+        // SAFETY: `trait_idx` is the index of `D`
         #[allow(unsafe_code)]
         unsafe {
-            let any = erased.to_ptr::<dyn AnyTrait>();
+            return cast_idx::<Self, D>(self, trait_idx);
+        }
+    }
 
-            return any.as_ref();
+    /// (Up/Down)cast to a mut ref, or fail to compile if the type
+    /// is not supported
+    #[inline]
+    fn static_cast_mut<D: ?Sized + 'static>(&mut self) -> &mut D {
+        let trait_idx = const {
+            match static_trait_idx::<Self, D>() {
+                Some(trait_idx) => trait_idx,
+                None => panic!("AnyTraitStatic: static_cast to unlisted type"),
+            }
+        };
+        // SAFETY: `trait_idx` is the index of `D`
+        #[allow(unsafe_code)]
+        unsafe {
+            return cast_idx_mut::<Self, D>(self, trait_idx);
         }
     }
-    /// upcast to `&mut dyn AnyTrait`
-    fn as_anytrait_mut(&mut self) -> &mut dyn AnyTrait {
-        let erased = self.type_erase(0);
+
+    /// Same as `AnyTraitCast::cast_ref`, but the search is done
+    /// at compile time
+    #[inline]
+    fn static_try_cast<D: ?Sized + 'static>(&self) -> Option<&D> {
+        let trait_idx = const { static_trait_idx::<Self, D>() };
+        let Some(trait_idx) = trait_idx else {
+            return None;
+        };
+        // SAFETY: `trait_idx` is the index of `D`
         #[allow(unsafe_code)]
         unsafe {
-            let mut any = erased.to_ptr::<dyn AnyTrait>();
+            return Some(cast_idx::<Self, D>(self, trait_idx));
+        }
+    }
 
-            return any.as_mut();
+    /// Same as `AnyTraitCast::cast_mut`, but the search is done
+    /// at compile time
+    #[inline]
+    fn static_try_cast_mut<D: ?Sized + 'static>(&mut self) -> Option<&mut D> {
+        let trait_idx = const { static_trait_idx::<Self, D>() };
+        let Some(trait_idx) = trait_idx else {
+            return None;
+        };
+        // SAFETY: `trait_idx` is the index of `D`
+        #[allow(unsafe_code)]
+        unsafe {
+            return Some(cast_idx_mut::<Self, D>(self, trait_idx));
         }
     }
 }
 
+/// const-find `D` in the `TYPE_IDS` of `T`
+#[allow(clippy::needless_return)]
+pub const fn static_trait_idx<
+    T: AnyTraitStatic + ?Sized,
+    D: ?Sized + 'static,
+>() -> Option<usize> {
+    let t = TypeIdConst::of::<D>();
+    let mut i: usize = 0;
+    while i < T::TYPE_IDS.len() {
+        if T::TYPE_IDS[i].eq(&t) {
+            return Some(i);
+        }
+        i = i + 1;
+    }
+    return None;
+}
+
+/// Check at compile time if `T` can be cast to `D`
+///
+/// example: `const OK: bool = implements::<Concrete, dyn TA>();`
+#[allow(clippy::needless_return)]
+pub const fn implements<T: AnyTraitStatic + ?Sized, D: ?Sized + 'static>()
+-> bool {
+    return static_trait_idx::<T, D>().is_some();
+}
+
+// The derive upcasts natively in `upcast_anytrait`, and `dyn TB` with
+// `TB: AnyTrait` calls it through the vtable.
+// Hand-written impls fall back to `type_erase(0)`
+#[allow(clippy::needless_return)]
+impl<T: AnyTrait + ?Sized> AsAnyTrait for T {
+    /// upcast to `&dyn AnyTrait`
+    #[inline]
+    fn as_anytrait(&self) -> &dyn AnyTrait {
+        return self.upcast_anytrait();
+    }
+    /// upcast to `&mut dyn AnyTrait`
+    #[inline]
+    fn as_anytrait_mut(&mut self) -> &mut dyn AnyTrait {
+        return self.upcast_anytrait_mut();
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<T> AsAnyTraitSend for T
+where
+    T: AnyTrait + Send + ?Sized + Unsize<AnyTraitSend>,
+{
+    /// upcast to `&AnyTraitSend`
+    #[inline]
+    fn as_anytrait_send(&self) -> &AnyTraitSend {
+        return self;
+    }
+    /// upcast to `&mut AnyTraitSend`
+    #[inline]
+    fn as_anytrait_send_mut(&mut self) -> &mut AnyTraitSend {
+        return self;
+    }
+    /// upcast to `Box<AnyTraitSend>`
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn into_anytrait_send(
+        self: ::alloc::boxed::Box<Self>,
+    ) -> ::alloc::boxed::Box<AnyTraitSend> {
+        return self;
+    }
+}
+
+#[allow(clippy::needless_return)]
+impl<T> AsAnyTraitSync for T
+where
+    T: AnyTrait + Send + Sync + ?Sized + Unsize<AnyTraitSync>,
+{
+    /// upcast to `&AnyTraitSync`
+    #[inline]
+    fn as_anytrait_sync(&self) -> &AnyTraitSync {
+        return self;
+    }
+    /// upcast to `&mut AnyTraitSync`
+    #[inline]
+    fn as_anytrait_sync_mut(&mut self) -> &mut AnyTraitSync {
+        return self;
+    }
+    /// upcast to `Box<AnyTraitSync>`
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn into_anytrait_sync(
+        self: ::alloc::boxed::Box<Self>,
+    ) -> ::alloc::boxed::Box<AnyTraitSync> {
+        return self;
+    }
+}
+
+/// Cast `obj` to the `trait_idx` type in its `.type_ids()`
+///
+/// # Safety
+/// `obj.type_ids()[trait_idx]` must be `D`
+#[inline]
+#[allow(unsafe_code, clippy::needless_return)]
+pub(crate) unsafe fn cast_idx<T: AnyTrait + ?Sized, D: ?Sized + 'static>(
+    obj: &T,
+    trait_idx: usize,
+) -> &D {
+    let erased = obj.type_erase(trait_idx);
+    #[cfg(any(feature = "checked", debug_assertions))]
+    erased.assert_type(obj.type_ids()[trait_idx]);
+    // SAFETY: `type_erase(trait_idx)` erased a `D`
+    unsafe {
+        let any = erased.to_ptr::<D>();
+
+        return any.as_ref();
+    }
+}
+
+/// Cast `obj` to the `trait_idx` type in its `.type_ids()`
+///
+/// # Safety
+/// `obj.type_ids()[trait_idx]` must be `D`
+#[inline]
+#[allow(unsafe_code, clippy::needless_return)]
+pub(crate) unsafe fn cast_idx_mut<T: AnyTrait + ?Sized, D: ?Sized + 'static>(
+    obj: &mut T,
+    trait_idx: usize,
+) -> &mut D {
+    let erased = obj.type_erase_mut(trait_idx);
+    #[cfg(any(feature = "checked", debug_assertions))]
+    erased.assert_type(obj.type_ids()[trait_idx]);
+    // SAFETY: `type_erase_mut(trait_idx)` erased a `D`
+    unsafe {
+        let mut any = erased.to_ptr::<D>();
+
+        return any.as_mut();
+    }
+}
+
+#[allow(clippy::needless_return, clippy::question_mark)]
 impl<T: AnyTrait + ?Sized> AnyTraitCast for T {
     /// Search the list of possible traits.
     ///
@@ -212,6 +652,35 @@ impl<T: AnyTrait + ?Sized> AnyTraitCast for T {
         */
     }
 
+    /// Search the list of possible traits.
+    #[inline]
+    fn implements<D: ?Sized + 'static>(&self) -> bool {
+        return self.trait_idx::<D>().is_some();
+    }
+
+    /// Search the list of possible traits only once,
+    /// and check that every type in `L` was found
+    #[inline]
+    fn implements_all<L: TraitList>(&self) -> bool {
+        const { assert!(L::TYPE_IDS.len() < 32, "TraitList too long") }
+        let mut found: u32 = 0;
+        for t in self.type_ids().iter() {
+            for (idx, l) in L::TYPE_IDS.iter().enumerate() {
+                if t == l {
+                    found |= 1 << idx;
+                }
+            }
+        }
+        return found == (1 << L::TYPE_IDS.len()) - 1;
+    }
+
+    /// Search the list of possible traits only once,
+    /// and stop at the first type in `L`
+    #[inline]
+    fn implements_any<L: TraitList>(&self) -> bool {
+        return self.type_ids().iter().any(|t| L::TYPE_IDS.contains(t));
+    }
+
     /// Safe cast to reference to a generic type.
     ///
     /// Only return Some(...) if it is safe to do so.
@@ -221,12 +690,10 @@ impl<T: AnyTrait + ?Sized> AnyTraitCast for T {
             return None;
         };
 
-        let erased = self.type_erase(trait_idx);
+        // SAFETY: `trait_idx` is the index of `D`
         #[allow(unsafe_code)]
         unsafe {
-            let any = erased.to_ptr::<D>();
-
-            return Some(any.as_ref());
+            return Some(cast_idx::<Self, D>(self, trait_idx));
         }
     }
 
@@ -239,12 +706,120 @@ impl<T: AnyTrait + ?Sized> AnyTraitCast for T {
             return None;
         };
 
-        let erased = self.type_erase_mut(trait_idx);
+        // SAFETY: `trait_idx` is the index of `D`
         #[allow(unsafe_code)]
         unsafe {
-            let mut any = erased.to_ptr::<D>();
+            return Some(cast_idx_mut::<Self, D>(self, trait_idx));
+        }
+    }
 
-            return Some(any.as_mut());
+    /// Search the list of possible traits only once,
+    /// then type-erase once per type in `L`
+    #[inline]
+    fn cast_many<L: TraitList>(&self) -> Option<L::Refs<'_>> {
+        const { assert!(L::TYPE_IDS.len() <= traitlist::MAX_LEN) }
+        let mut found = [usize::MAX; traitlist::MAX_LEN];
+        let found = &mut found[..L::TYPE_IDS.len()];
+        for (t_idx, t) in self.type_ids().iter().enumerate() {
+            for (l_idx, l) in L::TYPE_IDS.iter().enumerate() {
+                if t == l && found[l_idx] == usize::MAX {
+                    found[l_idx] = t_idx;
+                }
+            }
+        }
+        if found.contains(&usize::MAX) {
+            return None;
+        }
+        return Some(L::cast_from(self, found));
+    }
+
+    /// Safe cast to reference to a generic type.
+    ///
+    /// On failure, return what went wrong
+    #[inline]
+    fn try_cast_ref<D: ?Sized + 'static>(&self) -> Result<&D, CastError> {
+        let Some(trait_idx) = self.trait_idx::<D>() else {
+            return Err(CastError::new::<D>(
+                self.type_ids(),
+                self.trait_names(),
+            ));
+        };
+
+        // SAFETY: `trait_idx` is the index of `D`
+        #[allow(unsafe_code)]
+        unsafe {
+            return Ok(cast_idx::<Self, D>(self, trait_idx));
+        }
+    }
+
+    /// Safe cast to mutable reference to a generic type.
+    ///
+    /// On failure, return what went wrong
+    #[inline]
+    fn try_cast_mut<D: ?Sized + 'static>(
+        &mut self,
+    ) -> Result<&mut D, CastError> {
+        let Some(trait_idx) = self.trait_idx::<D>() else {
+            return Err(CastError::new::<D>(
+                self.type_ids(),
+                self.trait_names(),
+            ));
+        };
+
+        // SAFETY: `trait_idx` is the index of `D`
+        #[allow(unsafe_code)]
+        unsafe {
+            return Ok(cast_idx_mut::<Self, D>(self, trait_idx));
+        }
+    }
+
+    /// Type-erase to a safe `AnyRef`
+    ///
+    /// `type_erase` and `type_ids` are generated together, so the type of
+    /// the erased pointer is always `type_ids()[trait_num]`
+    #[inline]
+    fn erase_ref(&self, trait_num: usize) -> AnyRef<'_> {
+        let type_id = self.type_ids()[trait_num];
+        let erased = self.type_erase(trait_num);
+        #[allow(unsafe_code)]
+        unsafe {
+            return AnyRef::from_raw(erased, type_id);
         }
     }
+
+    /// Type-erase to a safe `AnyMut`
+    ///
+    /// `type_erase_mut` and `type_ids` are generated together, so the type
+    /// of the erased pointer is always `type_ids()[trait_num]`
+    #[inline]
+    fn erase_mut(&mut self, trait_num: usize) -> AnyMut<'_> {
+        let type_id = self.type_ids()[trait_num];
+        let erased = self.type_erase_mut(trait_num);
+        #[allow(unsafe_code)]
+        unsafe {
+            return AnyMut::from_raw(erased, type_id);
+        }
+    }
+
+    /// Call `f` on `self` and on all its children (recursively)
+    /// that can be cast to `D`
+    #[inline]
+    fn cast_all<D, F>(&self, walk: Walk, mut f: F)
+    where
+        D: ?Sized + 'static,
+        F: FnMut(&D),
+    {
+        children::walk::<Self, D>(self, walk, 0, &mut f);
+    }
+
+    /// Call `f` on `self` and on all its children (recursively)
+    /// that can be cast to `D`
+    #[inline]
+    fn cast_all_mut<D, F>(&mut self, walk: Walk, mut f: F)
+    where
+        D: ?Sized + 'static,
+        F: FnMut(&mut D),
+    {
+        children::walk_mut::<Self, D>(self, walk, 0, &mut f);
+    }
 }
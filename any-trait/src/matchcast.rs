@@ -0,0 +1,160 @@
+//! Dispatch on the first type an object can be cast to
+//!
+//! A chain of `if let Some(x) = obj.cast_ref::<dyn A>()` searches the
+//! `type_ids()` list once per branch.\
+//! `match_cast!` searches it once for all the arms.
+//!
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, match_cast};
+//! trait Shape {
+//!     fn area(&self) -> usize;
+//! }
+//! trait Named {
+//!     fn name(&self) -> &'static str;
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(Shape)]
+//! struct Square {
+//!     side: usize,
+//! }
+//! impl Shape for Square {
+//!     fn area(&self) -> usize {
+//!         self.side * self.side
+//!     }
+//! }
+//!
+//! let sq = Square { side: 3 };
+//! let obj: &dyn AnyTrait = &sq;
+//! let res = match_cast!(obj,
+//!     n: &dyn Named => n.name().len(),
+//!     s: &dyn Shape => s.area(),
+//!     Square as sq => sq.side,
+//!     _ => 0,
+//! );
+//! assert_eq!(res, 9);
+//! ```
+//!
+//! Arms:
+//! * `x: &Type => expr`: bind `x` as `&Type`
+//! * `x: &mut Type => expr`: bind `x` as `&mut Type`
+//! * `Type as x => expr`: same as `x: &Type`
+//! * `Type as mut x => expr`: same as `x: &mut Type`
+//! * `_ => expr`: mandatory last arm, if nothing matched
+//!
+//! The first arm in source order that matches is the one that runs.
+//!
+//! `obj` is evaluated once and moved into the macro, so pass a reference.
+//! If you need `obj` afterwards, reborrow it: `match_cast!(&mut *obj, ..)`
+
+use crate::typeidconst::TypeIdConst;
+
+/// Find the first of `arms` that is in `type_ids`, in one pass over
+/// `type_ids`
+///
+/// Returns `(arm, trait_idx)`: the index in `arms` and the index
+/// in `type_ids`
+#[doc(hidden)]
+#[allow(clippy::needless_return)]
+pub fn find_first_arm(
+    type_ids: &[TypeIdConst],
+    arms: &[TypeIdConst],
+) -> Option<(usize, usize)> {
+    let mut found: Option<(usize, usize)> = None;
+    for (trait_idx, t) in type_ids.iter().enumerate() {
+        // only the arms before the one we already found are interesting
+        let limit = match found {
+            Some((arm, _)) => arm,
+            None => arms.len(),
+        };
+        if let Some(arm) = arms[..limit].iter().position(|a| a == t) {
+            if arm == 0 {
+                return Some((arm, trait_idx));
+            }
+            found = Some((arm, trait_idx));
+        }
+    }
+    return found;
+}
+
+/// Dispatch on the first type an object can be cast to
+///
+/// see the `matchcast` module for the syntax
+#[macro_export]
+macro_rules! match_cast {
+    // entry point: normalize the arms, then generate the dispatch
+    ($obj:expr, $($arms:tt)+) => {
+        $crate::match_cast!(@parse [$obj] [] $($arms)+)
+    };
+
+    // normalize every arm to `(ref|mut name [Type] body)`
+    (@parse [$obj:expr] [$($done:tt)*] _ => $default:expr $(,)?) => {
+        $crate::match_cast!(@gen [$obj] [$($done)*] $default)
+    };
+    (@parse [$obj:expr] [$($done:tt)*]
+        $name:ident : &mut $t:ty => $body:expr, $($rest:tt)+) => {
+        $crate::match_cast!(@parse [$obj]
+            [$($done)* (mut $name [$t] $body)] $($rest)+)
+    };
+    (@parse [$obj:expr] [$($done:tt)*]
+        $name:ident : & $t:ty => $body:expr, $($rest:tt)+) => {
+        $crate::match_cast!(@parse [$obj]
+            [$($done)* (ref $name [$t] $body)] $($rest)+)
+    };
+    (@parse [$obj:expr] [$($done:tt)*]
+        $t:ty as mut $name:ident => $body:expr, $($rest:tt)+) => {
+        $crate::match_cast!(@parse [$obj]
+            [$($done)* (mut $name [$t] $body)] $($rest)+)
+    };
+    (@parse [$obj:expr] [$($done:tt)*]
+        $t:ty as $name:ident => $body:expr, $($rest:tt)+) => {
+        $crate::match_cast!(@parse [$obj]
+            [$($done)* (ref $name [$t] $body)] $($rest)+)
+    };
+
+    // single search for all the arms, then run the one we found
+    (@gen [$obj:expr]
+        [$(($kind:ident $name:ident [$t:ty] $body:expr))*] $default:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::{AnyTrait as _, AnyTraitCast as _};
+        let obj = $obj;
+        let found = $crate::matchcast::find_first_arm(
+            obj.type_ids(),
+            &[$($crate::typeidconst::TypeIdConst::of::<$t>()),*],
+        );
+        $crate::match_cast!(@chain [obj found] [0]
+            [$(($kind $name [$t] $body))*] $default)
+    }};
+
+    // one `if` per arm, `$n` counts the arms
+    (@chain [$obj:ident $found:ident] [$($n:tt)+] [] $default:expr) => {
+        $default
+    };
+    (@chain [$obj:ident $found:ident] [$($n:tt)+]
+        [(ref $name:ident [$t:ty] $body:expr) $($rest:tt)*] $default:expr) => {
+        match $found {
+            Some((arm, trait_idx)) if arm == $($n)+ => {
+                let $name: &$t = $obj
+                    .erase_ref(trait_idx)
+                    .downcast::<$t>()
+                    .expect("match_cast: wrong type index");
+                $body
+            }
+            _ => $crate::match_cast!(@chain [$obj $found] [$($n)+ + 1]
+                [$($rest)*] $default),
+        }
+    };
+    (@chain [$obj:ident $found:ident] [$($n:tt)+]
+        [(mut $name:ident [$t:ty] $body:expr) $($rest:tt)*] $default:expr) => {
+        match $found {
+            Some((arm, trait_idx)) if arm == $($n)+ => {
+                let $name: &mut $t = $obj
+                    .erase_mut(trait_idx)
+                    .downcast::<$t>()
+                    .expect("match_cast: wrong type index");
+                $body
+            }
+            _ => $crate::match_cast!(@chain [$obj $found] [$($n)+ + 1]
+                [$($rest)*] $default),
+        }
+    };
+}
@@ -0,0 +1,67 @@
+//! Lists of types, to check or cast many traits at once
+//!
+//! Rust does not let us write `(dyn A, dyn B)`: only the last element
+//! of a tuple can be unsized.\
+//! So we use tuples of references instead: `(&dyn A, &dyn B)`.
+//!
+//! The lifetimes of the references are ignored.
+
+use crate::{AnyTrait, AnyTraitCast, typeidconst::TypeIdConst};
+
+/// Maximum number of types in a `TraitList`
+pub const MAX_LEN: usize = 8;
+
+/// A tuple of references to the types we want to check or cast to.
+///
+/// example: `(&dyn TA, &dyn TB, &Concrete)`
+///
+/// **Automatically implemented on tuples of up to 8 references**
+pub trait TraitList {
+    /// `TypeIdConst` of the referenced types, in the same order as the tuple
+    const TYPE_IDS: &'static [TypeIdConst];
+
+    /// Same tuple, with the references bound to `'a`
+    type Refs<'a>;
+
+    /// Cast `obj` to every type in the list.
+    ///
+    /// `idx[n]` is the index in `obj.type_ids()` of the `n`-th type
+    ///
+    /// # Panics
+    /// If the indexes don't match the types
+    fn cast_from<'a, T: AnyTrait + ?Sized>(
+        obj: &'a T,
+        idx: &[usize],
+    ) -> Self::Refs<'a>;
+}
+
+macro_rules! impl_trait_list {
+    ($($t:ident $n:tt),+) => {
+        impl<$($t: ?Sized + 'static),+> TraitList for ($(&$t,)+) {
+            const TYPE_IDS: &'static [TypeIdConst] =
+                &[$(TypeIdConst::of::<$t>()),+];
+
+            type Refs<'a> = ($(&'a $t,)+);
+
+            fn cast_from<'a, T: AnyTrait + ?Sized>(
+                obj: &'a T,
+                idx: &[usize],
+            ) -> Self::Refs<'a> {
+                return ($(
+                    obj.erase_ref(idx[$n])
+                        .downcast::<$t>()
+                        .expect("TraitList: wrong type index"),
+                )+);
+            }
+        }
+    };
+}
+
+impl_trait_list!(A 0);
+impl_trait_list!(A 0, B 1);
+impl_trait_list!(A 0, B 1, C 2);
+impl_trait_list!(A 0, B 1, C 2, D 3);
+impl_trait_list!(A 0, B 1, C 2, D 3, E 4);
+impl_trait_list!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_trait_list!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_trait_list!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
@@ -9,17 +9,50 @@
 
 /// TypeId, but const-comparable
 /// TODO: Make sortable somehow? soundness problems on order. plz help
+///
+/// With the `type_names` feature (on by default) we also keep the
+/// `core::any::type_name` of the type, mostly for debugging.\
+/// The name is never used for comparisons or hashing.
 #[derive(Copy, Clone)]
 pub struct TypeIdConst {
     t: ::core::any::TypeId,
+    #[cfg(feature = "type_names")]
+    name: Option<&'static str>,
 }
 
+#[allow(clippy::needless_return)]
 impl TypeIdConst {
     pub const fn of<T: ?Sized + 'static>() -> TypeIdConst {
         return TypeIdConst {
             t: ::core::any::TypeId::of::<T>(),
+            #[cfg(feature = "type_names")]
+            name: Some(::core::any::type_name::<T>()),
         };
     }
+    /// Wrap a `core::any::TypeId`.
+    ///
+    /// We can't know the type name from here, so `.name()` will be `None`
+    pub const fn from_type_id(t: ::core::any::TypeId) -> TypeIdConst {
+        return TypeIdConst {
+            t,
+            #[cfg(feature = "type_names")]
+            name: None,
+        };
+    }
+    /// Get the inner `core::any::TypeId`
+    pub const fn type_id(&self) -> ::core::any::TypeId {
+        return self.t;
+    }
+    /// `core::any::type_name` of the type
+    ///
+    /// Only available with the `type_names` feature,
+    /// and only if we were created with `TypeIdConst::of`
+    pub const fn name(&self) -> Option<&'static str> {
+        #[cfg(feature = "type_names")]
+        return self.name;
+        #[cfg(not(feature = "type_names"))]
+        return None;
+    }
     pub const fn eq(&self, other: &Self) -> bool {
         return self.t.eq(&other.t);
     }
@@ -65,6 +98,41 @@ impl ::core::cmp::PartialEq for TypeIdConst {
     }
 }
 impl ::core::cmp::Eq for TypeIdConst {}
+impl ::core::cmp::PartialEq<::core::any::TypeId> for TypeIdConst {
+    fn eq(&self, other: &::core::any::TypeId) -> bool {
+        self.t.eq(other)
+    }
+}
+impl ::core::cmp::PartialEq<TypeIdConst> for ::core::any::TypeId {
+    fn eq(&self, other: &TypeIdConst) -> bool {
+        self.eq(&other.t)
+    }
+}
+impl ::core::hash::Hash for TypeIdConst {
+    fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+        self.t.hash(state)
+    }
+}
+/// Prints the type name with the `type_names` feature (on by default),
+/// otherwise just the opaque `TypeId`
+impl ::core::fmt::Debug for TypeIdConst {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+        match self.name() {
+            Some(name) => f.debug_tuple("TypeIdConst").field(&name).finish(),
+            None => f.debug_tuple("TypeIdConst").field(&self.t).finish(),
+        }
+    }
+}
+impl From<::core::any::TypeId> for TypeIdConst {
+    fn from(t: ::core::any::TypeId) -> Self {
+        TypeIdConst::from_type_id(t)
+    }
+}
+impl From<TypeIdConst> for ::core::any::TypeId {
+    fn from(t: TypeIdConst) -> Self {
+        t.t
+    }
+}
 
 /* Waiting for const Cmp on TypeID
 impl ::core::cmp::PartialOrd for TypeIdConst {
@@ -110,14 +178,56 @@ pub const fn sort_array<const N: usize>(
 /// get `[TypeIdConst;N]` in input and return `[TypeIdConst;N + 2]`
 ///
 /// we add the first two elements, and they always are:
-/// * `TypeIdConst::of::<dyn AnyTrait>`
+/// * `TypeIdConst::of::<dyn AnyTrait>`, or `TypeIdConst::of::<T>` again if `T`
+///   is not `sized`
 /// * `TypeIdConst::of::<T>`
-pub const fn append_array<T: 'static, const N: usize, const M: usize>(
+pub const fn append_array<
+    T: ?Sized + 'static,
+    const N: usize,
+    const M: usize,
+>(
+    sized: bool,
     array: [TypeIdConst; N],
 ) -> [TypeIdConst; M] {
     assert!(N + 2 == M, "M needs to be N + 2");
     let mut out: [TypeIdConst; M] = [TypeIdConst::of::<T>(); M];
-    out[0] = TypeIdConst::of::<dyn super::AnyTrait>();
+    if sized {
+        out[0] = TypeIdConst::of::<dyn super::AnyTrait>();
+    }
+    let mut i: usize = 2;
+    while i < M {
+        out[i] = array[i - 2];
+        i = i + 1;
+    }
+
+    out
+}
+
+/// const `core::any::type_name`, usable from the derive macro
+#[allow(clippy::needless_return)]
+pub const fn type_name<T: ?Sized>() -> &'static str {
+    return ::core::any::type_name::<T>();
+}
+
+/// get `[&'static str;N]` in input and return `[&'static str;N + 2]`
+///
+/// Same as `append_array`, but for the type names.
+/// we add the first two elements, and they always are:
+/// * `type_name::<dyn AnyTrait>`, or `type_name::<T>` if `T` is not `sized`
+/// * `type_name::<T>`
+pub const fn append_names<
+    T: ?Sized + 'static,
+    const N: usize,
+    const M: usize,
+>(
+    sized: bool,
+    array: [&'static str; N],
+) -> [&'static str; M] {
+    assert!(N + 2 == M, "M needs to be N + 2");
+    let mut out: [&'static str; M] = [type_name::<T>(); M];
+    if sized {
+        out[0] = type_name::<dyn super::AnyTrait>();
+    }
     let mut i: usize = 2;
     while i < M {
         out[i] = array[i - 2];
@@ -127,6 +237,14 @@ pub const fn append_array<T: 'static, const N: usize, const M: usize>(
     out
 }
 
+/// `true` if `type_ids[idx]` is not listed earlier, so we index each type
+/// only once, with the index that `cast_ref` would find
+#[cfg(feature = "alloc")]
+#[allow(clippy::needless_return)]
+pub(crate) fn first_listed(type_ids: &[TypeIdConst], idx: usize) -> bool {
+    return !type_ids[..idx].contains(&type_ids[idx]);
+}
+
 /// const-find the `TypeIdConst` of `T` inside the given array.
 /// return its index or panic
 pub const fn find_in<T: ?Sized + 'static, const N: usize>(
@@ -0,0 +1,213 @@
+//! Recover a `dyn AnyTrait` from a trait object that does not extend it
+//!
+//! A `&dyn TA` is a pointer to the data and a pointer to the vtable of
+//! `TA` for the concrete type. If we know the vtables of a type we can go
+//! back from the vtable to the type, and from there to `dyn AnyTrait`.
+//!
+//! This is opt-in: add `#[any_vtables]` to the derive, then `register` the
+//! type once:
+//! ```rust
+//! use any_trait::{AnySubTrait, AnyTrait, AnyTraitCast, vtable};
+//! trait TA {}
+//! trait TB {
+//!     fn b(&self) -> u32;
+//! }
+//! #[derive(AnySubTrait)]
+//! #[any_sub_trait(TA, TB)]
+//! #[any_vtables]
+//! struct Concrete {}
+//! impl TA for Concrete {}
+//! impl TB for Concrete {
+//!     fn b(&self) -> u32 {
+//!         42
+//!     }
+//! }
+//!
+//! vtable::register::<Concrete>();
+//!
+//! let c = Concrete {};
+//! let ta: &dyn TA = &c;
+//! // SAFETY: `Concrete` is registered, and its `TA` vtable is not
+//! // identical to the one of any other type
+//! let any = unsafe { any_trait::recover::<dyn TA>(ta) };
+//! if cfg!(miri) {
+//!     // Miri makes a new vtable every time, see the caveats
+//!     return;
+//! }
+//! let any = any.unwrap();
+//! assert_eq!(any.cast_ref::<dyn TB>().unwrap().b(), 42);
+//! ```
+//!
+//! # Caveats
+//!
+//! Rust does not guarantee that a vtable is unique:
+//! * the same vtable can be duplicated in different codegen units or crates. If
+//!   the `&dyn TA` was built in one and the type was registered in another,
+//!   `recover` does not find it and returns `None`. Miri always makes a new
+//!   vtable, so `recover` never works there.
+//! * the vtables of different types can be merged if they are identical (same
+//!   size, alignment, drop and methods). If two registered types share a vtable
+//!   `recover` returns `None`, but if one of them is not registered it will be
+//!   recovered as the registered one.
+//!
+//! The last one is undefined behaviour, so `recover` is `unsafe`.
+//! Only use this for types whose trait impls are not trivially identical,
+//! and prefer traits that extend `AnyTrait` when you can.
+
+use crate::{
+    AnyTrait, AnyTraitStatic,
+    anyptr::{AnyPtr, Erasable},
+    typeidconst::TypeIdConst,
+};
+use ::core::{
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+/// The vtables of a type, as a node of the global registry
+///
+/// **don't build this yourself, use `#[any_vtables]`**
+pub struct VTables {
+    next: AtomicPtr<VTables>,
+    registered: AtomicBool,
+    type_ids: &'static [TypeIdConst],
+    /// `AnyPtr` to a dangling `dyn Trait`, for each index of `type_ids`
+    vtable: fn(usize) -> Option<AnyPtr>,
+    recover: fn(NonNull<()>) -> NonNull<dyn AnyTrait>,
+}
+
+#[allow(clippy::needless_return)]
+fn recover_as<T: AnyTrait>(data: NonNull<()>) -> NonNull<dyn AnyTrait> {
+    let ptr = data.cast::<T>().as_ptr() as *mut dyn AnyTrait;
+    return NonNull::new(ptr).unwrap();
+}
+
+#[allow(clippy::needless_return)]
+impl VTables {
+    /// **don't use. internal only, see `#[any_vtables]`**
+    #[doc(hidden)]
+    pub const fn new<T: AnyTraitStatic>(
+        vtable: fn(usize) -> Option<AnyPtr>,
+    ) -> Self {
+        return Self {
+            next: AtomicPtr::new(ptr::null_mut()),
+            registered: AtomicBool::new(false),
+            type_ids: T::TYPE_IDS,
+            vtable,
+            recover: recover_as::<T>,
+        };
+    }
+
+    /// Does `T` have `vtable` as its vtable for `type_id`?
+    fn matches(&self, type_id: TypeIdConst, vtable: NonNull<()>) -> bool {
+        let Some(trait_idx) = self.type_ids.iter().position(|t| *t == type_id)
+        else {
+            return false;
+        };
+        return match (self.vtable)(trait_idx) {
+            Some(ptr) => ptr.vtable() == Some(vtable),
+            None => false,
+        };
+    }
+}
+
+/// Implemented by `#[any_vtables]`, see `register`
+pub trait AnyVTables: AnyTraitStatic {
+    /// The vtables of this type, as a static node
+    fn vtables() -> &'static VTables;
+}
+
+/// Head of the registry. Nodes are `'static` and never removed
+static HEAD: AtomicPtr<VTables> = AtomicPtr::new(ptr::null_mut());
+
+/// Add `T` to the types that `recover` can find.
+///
+/// Registering a type more than once does nothing
+pub fn register<T: AnyVTables>() {
+    let node = T::vtables();
+    if node.registered.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let node_ptr = node as *const VTables as *mut VTables;
+    let mut head = HEAD.load(Ordering::Acquire);
+    loop {
+        node.next.store(head, Ordering::Relaxed);
+        match HEAD.compare_exchange_weak(
+            head,
+            node_ptr,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => return,
+            Err(current) => head = current,
+        }
+    }
+}
+
+/// Find the only registered type with the vtable of `ptr`
+#[allow(clippy::needless_return)]
+fn find(ptr: AnyPtr, type_id: TypeIdConst) -> Option<&'static VTables> {
+    let vtable = ptr.vtable()?;
+    let mut found: Option<&'static VTables> = None;
+    let mut current = HEAD.load(Ordering::Acquire);
+    while !current.is_null() {
+        // SAFETY: only `&'static VTables` are linked in `register`
+        #[allow(unsafe_code)]
+        let node: &'static VTables = unsafe { &*current };
+        if node.matches(type_id, vtable) {
+            if found.is_some() {
+                // merged vtables, we can't tell the types apart
+                return None;
+            }
+            found = Some(node);
+        }
+        current = node.next.load(Ordering::Acquire);
+    }
+    return found;
+}
+
+/// Get the `dyn AnyTrait` behind a `dyn D`, if the concrete type was
+/// registered.
+///
+/// `D` should be a `dyn Trait`. See the module documentation for the caveats
+///
+/// # Safety
+/// The `D` vtable of the concrete type of `obj` must not be identical to the
+/// one of a different registered type, unless the concrete type is
+/// registered too. Otherwise `obj` can be recovered as the wrong type
+#[allow(clippy::needless_return, unsafe_code)]
+pub unsafe fn recover<D: ?Sized + Erasable + 'static>(
+    obj: &D,
+) -> Option<&dyn AnyTrait> {
+    let ptr = AnyPtr::from::<D>(obj as *const D);
+    let node = find(ptr, TypeIdConst::of::<D>())?;
+    let recovered = (node.recover)(ptr.data());
+    // SAFETY: the vtable of `D` for `T` matched, so the data is a `T`
+    // (the caller guarantees no other type shares it), borrowed for as
+    // long as `obj`
+    unsafe {
+        return Some(recovered.as_ref());
+    }
+}
+
+/// Get the `dyn AnyTrait` behind a `dyn D`, if the concrete type was
+/// registered.
+///
+/// `D` should be a `dyn Trait`. See the module documentation for the caveats
+///
+/// # Safety
+/// Same as `recover`
+#[allow(clippy::needless_return, unsafe_code)]
+pub unsafe fn recover_mut<D: ?Sized + Erasable + 'static>(
+    obj: &mut D,
+) -> Option<&mut dyn AnyTrait> {
+    let ptr = AnyPtr::from_mut::<D>(obj as *mut D);
+    let node = find(ptr, TypeIdConst::of::<D>())?;
+    let mut recovered = (node.recover)(ptr.data());
+    // SAFETY: the vtable of `D` for `T` matched, so the data is a `T`
+    // (the caller guarantees no other type shares it), borrowed mutably for
+    // as long as `obj`
+    unsafe {
+        return Some(recovered.as_mut());
+    }
+}
@@ -111,3 +111,1749 @@ fn multi_traits() {
         }
     }
 }
+
+#[cfg(any(feature = "checked", debug_assertions))]
+#[test]
+#[should_panic(expected = "does not match the expected type")]
+fn checked_wrong_erase() {
+    use any_trait::{anyptr::AnyPtr, typeidconst::TypeIdConst};
+    trait TA {}
+    struct C {}
+    impl TA for C {}
+    // hand-written and wrong: index 2 is erased as the concrete type
+    impl AnyTrait for C {
+        fn type_ids(&self) -> &'static [TypeIdConst] {
+            const TRAITS: [TypeIdConst; 3] = [
+                TypeIdConst::of::<dyn AnyTrait>(),
+                TypeIdConst::of::<C>(),
+                TypeIdConst::of::<dyn TA>(),
+            ];
+            &TRAITS
+        }
+        fn type_erase(&self, trait_num: usize) -> AnyPtr {
+            match trait_num {
+                0 => AnyPtr::from::<dyn AnyTrait>(self as *const dyn AnyTrait),
+                _ => AnyPtr::from::<C>(self as *const C),
+            }
+        }
+        fn type_erase_mut(&mut self, trait_num: usize) -> AnyPtr {
+            match trait_num {
+                0 => {
+                    AnyPtr::from_mut::<dyn AnyTrait>(self as *mut dyn AnyTrait)
+                }
+                _ => AnyPtr::from_mut::<C>(self as *mut C),
+            }
+        }
+    }
+
+    let c = C {};
+    assert!(c.type_name() == "<unknown>", "default trait_names");
+    let _ = c.as_anytrait().cast_ref::<dyn TA>();
+}
+
+#[test]
+fn any_ref() {
+    use any_trait::anyref::{AnyMut, AnyRef};
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    trait TB {
+        fn set(&mut self, val: usize);
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, TB)]
+    struct C {
+        val: usize,
+    }
+    impl TA for C {
+        fn get(&self) -> usize {
+            self.val
+        }
+    }
+    impl TB for C {
+        fn set(&mut self, val: usize) {
+            self.val = val;
+        }
+    }
+
+    let mut s = C { val: 42 };
+
+    let a = s.as_anytrait();
+    let idx = a.trait_idx::<dyn TA>().unwrap();
+    let r: AnyRef<'_> = a.erase_ref(idx);
+    assert!(r.is::<dyn TA>(), "AnyRef: wrong type");
+    assert!(r.downcast::<dyn TB>().is_none(), "AnyRef: downcast to TB");
+    assert!(r.downcast::<C>().is_none(), "AnyRef: downcast to C");
+    match r.downcast::<dyn TA>() {
+        None => panic!("AnyRef: can't downcast to TA"),
+        Some(ta_ref) => assert!(ta_ref.get() == 42, "TA get: {}", ta_ref.get()),
+    }
+
+    let a = s.as_anytrait_mut();
+    let idx = a.trait_idx::<dyn TB>().unwrap();
+    let mut m: AnyMut<'_> = a.erase_mut(idx);
+    assert!(
+        m.downcast_ref::<dyn TA>().is_none(),
+        "AnyMut: downcast to TA"
+    );
+    match m.downcast_mut::<dyn TB>() {
+        None => panic!("AnyMut: can't downcast to TB"),
+        Some(tb_mut) => tb_mut.set(7),
+    }
+    let r: AnyRef<'_> = m.into();
+    assert!(r.downcast::<dyn TB>().is_some(), "AnyMut -> AnyRef");
+
+    let r = AnyRef::new::<C>(&s);
+    assert!(r.downcast::<C>().unwrap().val == 7, "AnyRef: new");
+
+    // slices are told apart from `dyn Trait` by their metadata type
+    let bytes: &[u8] = &[1, 2, 3];
+    let r: AnyRef<'_> = bytes.into();
+    assert!(
+        r.downcast::<[u8]>() == Some(bytes),
+        "AnyRef: new with a slice"
+    );
+}
+
+#[test]
+fn slice_ptr() {
+    use any_trait::anyptr::AnyPtr;
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    impl TA for usize {
+        fn get(&self) -> usize {
+            *self
+        }
+    }
+
+    // odd, even and zero lengths
+    for len in [0, 1, 3, 4] {
+        let arr = [1u8, 2, 3, 4];
+        let s: &[u8] = &arr[..len];
+        let erased = AnyPtr::from_slice::<[u8]>(s as *const [u8]);
+        #[allow(unsafe_code)]
+        let back = unsafe { erased.to_ptr::<[u8]>().as_ref() };
+        assert!(back == s, "slice roundtrip with len {}", len);
+    }
+
+    let s: &str = "hello";
+    let erased = AnyPtr::from_slice::<str>(s as *const str);
+    #[allow(unsafe_code)]
+    let back = unsafe { erased.to_ptr::<str>().as_ref() };
+    assert!(back == "hello", "str roundtrip: {}", back);
+
+    let val: usize = 42;
+    let erased = AnyPtr::from::<dyn TA>(&val as &dyn TA as *const dyn TA);
+    #[allow(unsafe_code)]
+    let back = unsafe { erased.to_ptr::<dyn TA>().as_ref() };
+    assert!(back.get() == 42, "dyn roundtrip: {}", back.get());
+}
+
+#[test]
+fn unsized_concrete() {
+    #[derive(AnySubTrait)]
+    #[repr(C)]
+    struct Packet {
+        header: u8,
+        body: [u8],
+    }
+
+    let buf = [7u8, 1, 2, 3];
+    // `Packet` has the same layout as `[u8]`, minus the header
+    let ptr = ::core::ptr::slice_from_raw_parts(buf.as_ptr(), buf.len() - 1);
+    #[allow(unsafe_code)]
+    let p: &Packet = unsafe { &*(ptr as *const Packet) };
+
+    match p.cast_ref::<Packet>() {
+        None => panic!("can't cast to concrete"),
+        Some(p_ref) => {
+            assert!(p_ref.header == 7, "header: {}", p_ref.header);
+            assert!(p_ref.body == [1, 2, 3], "body: {:?}", &p_ref.body);
+        }
+    }
+    assert!(p.cast_ref::<[u8]>().is_none(), "cast to wrong type");
+    assert!(
+        p.cast_ref::<dyn AnyTrait>().is_none(),
+        "unsized types don't list dyn AnyTrait"
+    );
+    assert!(
+        p.cast_ref::<dyn ::core::any::Any>().is_none(),
+        "unsized types don't list dyn Any"
+    );
+    for idx in 0..p.type_ids().len() {
+        assert!(
+            p.erase_ref(idx).downcast::<Packet>().is_some(),
+            "row {} is not Packet",
+            idx
+        );
+    }
+
+    // unsized through a nested field
+    #[derive(AnySubTrait)]
+    #[repr(C)]
+    struct Outer {
+        tag: u8,
+        packet: Packet,
+    }
+    let ptr = ::core::ptr::slice_from_raw_parts(buf.as_ptr(), buf.len() - 2);
+    #[allow(unsafe_code)]
+    let o: &Outer = unsafe { &*(ptr as *const Outer) };
+    match o.cast_ref::<Outer>() {
+        None => panic!("can't cast nested to concrete"),
+        Some(o_ref) => {
+            assert!(o_ref.tag == 7, "tag: {}", o_ref.tag);
+            assert!(
+                o_ref.packet.body == [2, 3],
+                "body: {:?}",
+                &o_ref.packet.body
+            );
+        }
+    }
+
+    // generic, sized
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA)]
+    struct Wrapper<T> {
+        val: T,
+    }
+    impl<T: Copy + Into<usize> + 'static> TA for Wrapper<T> {
+        fn get(&self) -> usize {
+            self.val.into()
+        }
+    }
+    let w = Wrapper { val: 5u8 };
+    let a = w.as_anytrait();
+    assert!(a.cast_ref::<dyn TA>().unwrap().get() == 5, "generic cast");
+    assert!(a.cast_ref::<Wrapper<u8>>().is_some(), "generic concrete");
+    assert!(a.cast_ref::<Wrapper<u16>>().is_none(), "generic wrong type");
+}
+
+#[test]
+fn type_id_const() {
+    use any_trait::typeidconst::TypeIdConst;
+    use std::{any::TypeId, collections::HashSet};
+    trait TA {}
+
+    let ta = TypeIdConst::of::<dyn TA>();
+    let core_ta: TypeId = ta.into();
+    assert!(core_ta == TypeId::of::<dyn TA>(), "into TypeId");
+    assert!(ta == TypeId::of::<dyn TA>(), "eq TypeId");
+    assert!(TypeIdConst::from(core_ta) == ta, "from TypeId");
+    assert!(TypeIdConst::from(core_ta).name().is_none(), "unknown name");
+
+    let mut set = HashSet::new();
+    set.insert(ta);
+    set.insert(TypeIdConst::of::<usize>());
+    set.insert(TypeIdConst::from(core_ta));
+    assert!(set.len() == 2, "hash: {:?}", set);
+
+    let debug = format!("{:?}", ta);
+    #[cfg(feature = "type_names")]
+    {
+        assert!(ta.name() == Some(std::any::type_name::<dyn TA>()), "name");
+        assert!(debug.contains("TA"), "debug: {}", debug);
+    }
+    #[cfg(not(feature = "type_names"))]
+    assert!(debug.contains("TypeId"), "debug: {}", debug);
+}
+
+#[test]
+fn introspection() {
+    use any_trait::typeidconst::TypeIdConst;
+    use std::any::type_name;
+    trait TA {}
+    trait TB {}
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, TB)]
+    struct C {}
+    impl TA for C {}
+    impl TB for C {}
+
+    let s = C {};
+    let a = s.as_anytrait();
+
+    assert!(a.type_name() == type_name::<C>(), "name: {}", a.type_name());
+    let names = a.trait_names();
+    assert!(names.len() == a.type_ids().len(), "names len: {:?}", names);
+    for (idx, (id, name)) in a.traits().enumerate() {
+        assert!(id == a.type_ids()[idx], "traits() order");
+        assert!(name == names[idx], "traits() name order");
+    }
+    let tb_idx = a.trait_idx::<dyn TB>().unwrap();
+    assert!(names[tb_idx] == type_name::<dyn TB>(), "{}", names[tb_idx]);
+    assert!(
+        a.traits()
+            .any(|t| t == (TypeIdConst::of::<dyn TA>(), type_name::<dyn TA>())),
+        "missing TA"
+    );
+}
+
+#[test]
+fn cast_error() {
+    use any_trait::casterror::NearMiss;
+    trait TA {}
+    trait TB {}
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA)]
+    struct C {}
+    impl TA for C {}
+    impl TB for C {}
+
+    let mut s = C {};
+    assert!(s.try_cast_ref::<dyn TA>().is_ok(), "can't cast to TA");
+    assert!(s.try_cast_mut::<dyn TA>().is_ok(), "can't cast to mut TA");
+
+    let err = s.try_cast_ref::<dyn TB>().err().unwrap();
+    assert!(err.near_miss.is_none(), "near miss: {:?}", err.near_miss);
+    assert!(
+        err.concrete == std::any::type_name::<C>(),
+        "{}",
+        err.concrete
+    );
+    assert!(err.available.len() == 4, "{:?}", err.available);
+    let msg = err.to_string();
+    assert!(msg.contains("TB") && msg.contains("TA"), "message: {}", msg);
+
+    let err = s.try_cast_mut::<dyn TA + Send>().err().unwrap();
+    assert!(
+        err.near_miss == Some(NearMiss::AutoTraits { idx: 2 }),
+        "near miss: {:?}",
+        err.near_miss
+    );
+
+    // two traits in sibling blocks have the same `type_name`,
+    // but they are different types
+    let obj: Box<dyn AnyTrait> = {
+        trait TD {}
+        #[derive(AnySubTrait)]
+        #[any_sub_trait(TD)]
+        struct D {}
+        impl TD for D {}
+        Box::new(D {})
+    };
+    {
+        trait TD {}
+        let err = obj.try_cast_ref::<dyn TD>().err().unwrap();
+        assert!(
+            err.near_miss == Some(NearMiss::SameName { idx: 2 }),
+            "near miss: {:?}",
+            err.near_miss
+        );
+    }
+}
+
+#[test]
+fn implements() {
+    trait TA {}
+    trait TB {}
+    trait TC {}
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, TB)]
+    struct C {}
+    impl TA for C {}
+    impl TB for C {}
+
+    let s = C {};
+    let a = s.as_anytrait();
+
+    assert!(a.implements::<dyn TA>(), "implements TA");
+    assert!(a.implements::<C>(), "implements C");
+    assert!(!a.implements::<dyn TC>(), "implements TC");
+
+    assert!(a.implements_all::<(&dyn TA, &dyn TB)>(), "all TA, TB");
+    assert!(
+        a.implements_all::<(&dyn TB, &C, &dyn TA)>(),
+        "all TB, C, TA"
+    );
+    assert!(!a.implements_all::<(&dyn TA, &dyn TC)>(), "all TA, TC");
+
+    assert!(a.implements_any::<(&dyn TC, &dyn TB)>(), "any TC, TB");
+    assert!(!a.implements_any::<(&dyn TC,)>(), "any TC");
+}
+
+#[test]
+fn static_cast() {
+    use any_trait::AnyTraitStatic;
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    trait TB {
+        fn set(&mut self, val: usize);
+    }
+    trait TC {}
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, TB)]
+    struct C {
+        val: usize,
+    }
+    impl TA for C {
+        fn get(&self) -> usize {
+            self.val
+        }
+    }
+    impl TB for C {
+        fn set(&mut self, val: usize) {
+            self.val = val;
+        }
+    }
+    impl TC for C {}
+
+    const { assert!(any_trait::implements::<C, dyn TA>(), "C implements TA") };
+    const { assert!(!any_trait::implements::<C, dyn TC>(), "C does not list TC") };
+
+    // the derive must not add names to the type
+    #[derive(AnySubTrait)]
+    struct Own {}
+    impl Own {
+        const fn implements() -> bool {
+            true
+        }
+    }
+    const { assert!(Own::implements(), "own implements") };
+
+    let mut s = C { val: 42 };
+    assert!(C::TYPE_IDS == s.type_ids(), "TYPE_IDS != type_ids()");
+
+    s.static_cast_mut::<dyn TB>().set(7);
+    assert!(s.static_cast::<dyn TA>().get() == 7, "static_cast TA");
+    assert!(s.static_cast::<C>().val == 7, "static_cast C");
+    assert!(
+        s.static_try_cast::<dyn TC>().is_none(),
+        "static_try_cast TC"
+    );
+    assert!(
+        s.static_try_cast_mut::<dyn TB>().is_some(),
+        "static_try_cast TB"
+    );
+}
+
+#[test]
+fn cast_many() {
+    use std::fmt::Debug;
+    trait Draw {
+        fn draw(&self) -> usize;
+    }
+    trait Bounds {
+        fn bounds(&self) -> (usize, usize);
+    }
+    trait TC {}
+    #[derive(AnySubTrait, Debug)]
+    #[any_sub_trait(Draw, Bounds, Debug)]
+    struct C {
+        val: usize,
+    }
+    impl Draw for C {
+        fn draw(&self) -> usize {
+            self.val
+        }
+    }
+    impl Bounds for C {
+        fn bounds(&self) -> (usize, usize) {
+            (0, self.val)
+        }
+    }
+
+    let s = C { val: 42 };
+    let a = s.as_anytrait();
+
+    match a.cast_many::<(&dyn Draw, &dyn Bounds, &dyn Debug)>() {
+        None => panic!("can't cast_many"),
+        Some((draw, bounds, debug)) => {
+            assert!(draw.draw() == 42, "draw: {}", draw.draw());
+            assert!(bounds.bounds() == (0, 42), "bounds");
+            assert!(format!("{:?}", debug).contains("42"), "debug");
+        }
+    }
+    match a.cast_many::<(&C, &dyn Draw)>() {
+        None => panic!("can't cast_many to concrete"),
+        Some((c, draw)) => assert!(c.val == draw.draw(), "concrete"),
+    }
+    assert!(
+        a.cast_many::<(&dyn Draw, &dyn TC)>().is_none(),
+        "cast_many with unlisted trait"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn iter_filter_cast() {
+    use any_trait::iter::{AnyTraitIter, AnyTraitIterMut};
+    trait Tick {
+        fn tick(&mut self);
+        fn ticks(&self) -> usize;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Tick)]
+    struct Clock {
+        ticks: usize,
+    }
+    impl Tick for Clock {
+        fn tick(&mut self) {
+            self.ticks += 1;
+        }
+        fn ticks(&self) -> usize {
+            self.ticks
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Rock {}
+
+    let mut all: Vec<Box<dyn AnyTrait>> = vec![
+        Box::new(Clock { ticks: 0 }),
+        Box::new(Clock { ticks: 10 }),
+        Box::new(Rock {}),
+        Box::new(Rock {}),
+        Box::new(Clock { ticks: 20 }),
+    ];
+
+    for t in all.iter_mut().filter_cast_mut::<dyn Tick>() {
+        t.tick();
+    }
+    let ticks: Vec<usize> = all
+        .iter()
+        .filter_cast::<dyn Tick>()
+        .map(|t| t.ticks())
+        .collect();
+    assert!(ticks == [1, 11, 21], "ticks: {:?}", ticks);
+
+    let refs: Vec<&dyn AnyTrait> = all.iter().map(|b| &**b).collect();
+    let (yes, no): (Vec<&dyn Tick>, Vec<&dyn AnyTrait>) =
+        refs.iter().copied().partition_cast::<dyn Tick, _, _>();
+    assert!(yes.len() == 3, "partition yes: {}", yes.len());
+    assert!(no.len() == 2, "partition no: {}", no.len());
+    assert!(no.iter().all(|r| r.cast_ref::<Rock>().is_some()), "no Rock");
+
+    let mut refs: Vec<&mut dyn AnyTrait> =
+        all.iter_mut().map(|b| &mut **b).collect();
+    let count = refs
+        .iter_mut()
+        .map(|r| &mut **r)
+        .filter_cast_mut::<Clock>()
+        .count();
+    assert!(count == 3, "filter_cast_mut to concrete: {}", count);
+}
+
+#[test]
+fn cast_slice() {
+    use any_trait::iter::{cast_slice, cast_slice_mut};
+    trait Scale {
+        fn scale(&mut self, by: usize);
+        fn size(&self) -> usize;
+    }
+    trait TB {}
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Scale)]
+    struct Square {
+        side: usize,
+    }
+    impl Scale for Square {
+        fn scale(&mut self, by: usize) {
+            self.side *= by;
+        }
+        fn size(&self) -> usize {
+            self.side
+        }
+    }
+
+    let mut squares = [Square { side: 1 }, Square { side: 2 }];
+    for s in cast_slice_mut::<_, dyn Scale>(&mut squares).unwrap() {
+        s.scale(3);
+    }
+    let sizes: Vec<usize> = cast_slice::<_, dyn Scale>(&squares)
+        .unwrap()
+        .map(|s| s.size())
+        .collect();
+    assert!(sizes == [3, 6], "sizes: {:?}", sizes);
+    assert!(
+        cast_slice::<_, dyn TB>(&squares).is_none(),
+        "cast_slice to unlisted trait"
+    );
+    let empty: [Square; 0] = [];
+    assert!(
+        cast_slice::<_, dyn Scale>(&empty).unwrap().len() == 0,
+        "cast_slice on empty slice"
+    );
+    assert!(
+        cast_slice::<_, dyn TB>(&empty).is_none(),
+        "cast_slice on empty slice to unlisted trait"
+    );
+}
+
+#[test]
+fn match_cast() {
+    use any_trait::match_cast;
+    trait Shape {
+        fn area(&self) -> usize;
+    }
+    trait Grow {
+        fn grow(&mut self);
+    }
+    trait TC {}
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Shape, Grow)]
+    struct Square {
+        side: usize,
+    }
+    impl Shape for Square {
+        fn area(&self) -> usize {
+            self.side * self.side
+        }
+    }
+    impl Grow for Square {
+        fn grow(&mut self) {
+            self.side += 1;
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Rock {}
+
+    let mut sq = Square { side: 2 };
+    let obj: &mut dyn AnyTrait = &mut sq;
+
+    // first matching arm in source order, even if listed later
+    // in `type_ids()`
+    let res = match_cast!(&*obj,
+        _t: &dyn TC => 1,
+        s: &Square => s.side * 10,
+        s: &dyn Shape => s.area(),
+        _ => 0,
+    );
+    assert!(res == 20, "match_cast order: {}", res);
+
+    let res = match_cast!(&mut *obj,
+        Rock as _r => 1,
+        g: &mut dyn Grow => {
+            g.grow();
+            2
+        },
+        Square as mut s => {
+            s.side = 100;
+            3
+        },
+        _ => 0,
+    );
+    assert!(res == 2, "match_cast mut: {}", res);
+    assert!(sq.side == 3, "match_cast mut side: {}", sq.side);
+
+    let rock = Rock {};
+    let res = match_cast!(&rock,
+        s: &dyn Shape => s.area(),
+        _ => 42,
+    );
+    assert!(res == 42, "match_cast default: {}", res);
+}
+
+#[test]
+fn dispatch_table() {
+    use any_trait::dispatch::{DispatchArray, TableFull};
+    trait Shape {
+        fn size(&self) -> usize;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Shape)]
+    struct Circle {
+        r: usize,
+    }
+    impl Shape for Circle {
+        fn size(&self) -> usize {
+            self.r
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Shape)]
+    struct Square {
+        side: usize,
+    }
+    impl Shape for Square {
+        fn size(&self) -> usize {
+            self.side
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Rock {}
+
+    let c = Circle { r: 1 };
+    let s = Square { side: 10 };
+    let rock = Rock {};
+    let (c, s, rock): (&dyn AnyTrait, &dyn AnyTrait, &dyn AnyTrait) =
+        (&c, &s, &rock);
+
+    let mut table = DispatchArray::<usize, 2>::new();
+    table
+        .register::<Circle, Square>(|c, s| 100 + c.r + s.side)
+        .unwrap();
+    table
+        .register_fallback::<dyn Shape, dyn Shape>(|a, b| a.size() + b.size())
+        .unwrap();
+    assert!(
+        table.register::<Rock, Rock>(|_, _| 0) == Err(TableFull),
+        "DispatchArray capacity"
+    );
+    assert!(table.dispatch(c, s) == Some(111), "exact dispatch");
+    // no symmetric: the trait fallback is used
+    assert!(table.dispatch(s, c) == Some(11), "fallback dispatch");
+    assert!(table.dispatch(c, rock).is_none(), "no handler");
+
+    let table = table.symmetric(true);
+    assert!(table.dispatch(s, c) == Some(111), "symmetric dispatch");
+
+    #[cfg(feature = "alloc")]
+    {
+        use any_trait::dispatch::DispatchTable;
+        let offset = 1000;
+        let mut table = DispatchTable::<usize>::new().symmetric(true);
+        table.register::<Circle, Square, _>(move |c, s| offset + c.r + s.side);
+        table.register_fallback::<dyn Shape, Rock, _>(|a, _| a.size());
+        assert!(table.dispatch(s, c) == Some(1011), "symmetric closure");
+        assert!(table.dispatch(rock, s) == Some(10), "symmetric fallback");
+        table.register::<Circle, Square, _>(|_, _| 0);
+        assert!(table.dispatch(c, s) == Some(0), "replace handler");
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn any_vec() {
+    use any_trait::anyvec::AnyVec;
+    trait Update {
+        fn update(&mut self);
+        fn value(&self) -> usize;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Update)]
+    struct Player {
+        x: usize,
+    }
+    impl Update for Player {
+        fn update(&mut self) {
+            self.x += 1;
+        }
+        fn value(&self) -> usize {
+            self.x
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Update)]
+    struct Enemy {
+        hp: usize,
+    }
+    impl Update for Enemy {
+        fn update(&mut self) {
+            self.hp -= 1;
+        }
+        fn value(&self) -> usize {
+            self.hp
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Wall {}
+
+    let mut scene = AnyVec::new();
+    let p = scene.insert(Player { x: 0 });
+    let w = scene.insert(Wall {});
+    let e = scene.insert(Enemy { hp: 10 });
+    assert!(scene.len() == 3, "len: {}", scene.len());
+    assert!(scene.count::<dyn Update>() == 2, "count dyn Update");
+    assert!(scene.count::<dyn AnyTrait>() == 3, "count dyn AnyTrait");
+
+    for u in scene.iter_mut::<dyn Update>() {
+        u.update();
+    }
+    let values: Vec<usize> =
+        scene.iter::<dyn Update>().map(|u| u.value()).collect();
+    assert!(values == [1, 9], "values: {:?}", values);
+
+    assert!(scene.get::<Player>(p).unwrap().x == 1, "typed get");
+    assert!(scene.get::<Player>(e).is_none(), "typed get, wrong type");
+    scene.get_mut::<Enemy>(e).unwrap().hp = 100;
+
+    let wall = scene.remove(w).unwrap();
+    assert!(wall.cast_ref::<Wall>().is_some(), "removed Wall");
+    assert!(!scene.contains(w), "removed handle still valid");
+    assert!(scene.remove(w).is_none(), "removed twice");
+
+    // the slot of the wall is reused, the old handle must not see it
+    let p2 = scene.insert(Player { x: 50 });
+    assert!(scene.get_any(w).is_none(), "stale handle");
+    let handles: Vec<_> = scene
+        .iter_handles::<dyn Update>()
+        .map(|(h, u)| (h, u.value()))
+        .collect();
+    assert!(
+        handles == [(p, 1), (p2, 50), (e, 100)],
+        "handles: {:?}",
+        handles
+    );
+    for u in scene.iter_mut::<dyn Update>() {
+        u.update();
+    }
+    assert!(
+        scene.get::<Player>(p2).unwrap().x == 51,
+        "iter_mut after reuse"
+    );
+    assert!(scene.count::<Wall>() == 0, "Wall index not cleared");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn any_vec_duplicate_listing() {
+    use any_trait::{anyptr::AnyPtr, anyvec::AnyVec, typeidconst::TypeIdConst};
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    struct C {
+        val: usize,
+    }
+    impl TA for C {
+        fn get(&self) -> usize {
+            self.val
+        }
+    }
+    // hand-written, lists `dyn TA` twice
+    impl AnyTrait for C {
+        fn type_ids(&self) -> &'static [TypeIdConst] {
+            const TRAITS: [TypeIdConst; 4] = [
+                TypeIdConst::of::<dyn AnyTrait>(),
+                TypeIdConst::of::<C>(),
+                TypeIdConst::of::<dyn TA>(),
+                TypeIdConst::of::<dyn TA>(),
+            ];
+            &TRAITS
+        }
+        fn type_erase(&self, trait_num: usize) -> AnyPtr {
+            match trait_num {
+                0 => AnyPtr::from::<dyn AnyTrait>(self as *const dyn AnyTrait),
+                1 => AnyPtr::from::<C>(self as *const C),
+                _ => AnyPtr::from::<dyn TA>(self as *const dyn TA),
+            }
+        }
+        fn type_erase_mut(&mut self, trait_num: usize) -> AnyPtr {
+            match trait_num {
+                0 => {
+                    AnyPtr::from_mut::<dyn AnyTrait>(self as *mut dyn AnyTrait)
+                }
+                1 => AnyPtr::from_mut::<C>(self as *mut C),
+                _ => AnyPtr::from_mut::<dyn TA>(self as *mut dyn TA),
+            }
+        }
+    }
+
+    let mut v = AnyVec::new();
+    let a = v.insert(C { val: 1 });
+    v.insert(C { val: 2 });
+    assert!(v.count::<dyn TA>() == 2, "count: {}", v.count::<dyn TA>());
+    let values: Vec<usize> = v.iter_mut::<dyn TA>().map(|t| t.get()).collect();
+    assert!(values == [1, 2], "values: {:?}", values);
+
+    v.remove(a).unwrap();
+    assert!(v.count::<dyn TA>() == 1, "count after remove");
+    let values: Vec<usize> = v.iter::<dyn TA>().map(|t| t.get()).collect();
+    assert!(values == [2], "values after remove: {:?}", values);
+
+    // hand-written impls upcast through `type_erase_mut(0)`
+    let mut c = C { val: 3 };
+    c.as_anytrait_mut().cast_mut::<C>().unwrap().val = 4;
+    assert!(c.val == 4, "fallback as_anytrait_mut: {}", c.val);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn any_trait_map() {
+    use any_trait::anymap::AnyTraitMap;
+    trait Reload {
+        fn reload(&mut self);
+        fn count(&self) -> usize;
+    }
+    #[derive(AnySubTrait, Default)]
+    #[any_sub_trait(Reload)]
+    struct Config {
+        loaded: usize,
+    }
+    impl Reload for Config {
+        fn reload(&mut self) {
+            self.loaded += 1;
+        }
+        fn count(&self) -> usize {
+            self.loaded
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Reload)]
+    struct Assets {
+        loaded: usize,
+    }
+    impl Reload for Assets {
+        fn reload(&mut self) {
+            self.loaded += 10;
+        }
+        fn count(&self) -> usize {
+            self.loaded
+        }
+    }
+    #[derive(AnySubTrait, Debug, PartialEq)]
+    struct Time(u64);
+
+    let mut map = AnyTraitMap::new();
+    assert!(map.insert(Time(1)).is_none(), "first insert");
+    assert!(map.insert(Time(2)) == Some(Time(1)), "replace");
+    assert!(map.len() == 1, "len after replace: {}", map.len());
+
+    map.entry::<Config>().or_default().loaded = 5;
+    map.entry::<Config>()
+        .and_modify(|c| c.loaded += 1)
+        .or_insert(Config { loaded: 100 });
+    assert!(map.get::<Config>().unwrap().loaded == 6, "entry");
+    assert!(
+        map.insert_box(Box::new(Assets { loaded: 0 })).is_none(),
+        "insert_box"
+    );
+
+    for r in map.iter_cast_mut::<dyn Reload>() {
+        r.reload();
+    }
+    let total: usize = map.iter_cast::<dyn Reload>().map(|r| r.count()).sum();
+    assert!(total == 17, "iter_cast total: {}", total);
+    assert!(map.iter().count() == 3, "iter all");
+
+    assert!(map.remove::<Time>() == Some(Time(2)), "typed remove");
+    assert!(!map.contains::<Time>(), "removed");
+    assert!(map.get_mut::<Time>().is_none(), "get_mut removed");
+    let old = map.insert_box(Box::new(Assets { loaded: 0 })).unwrap();
+    assert!(old.cast_ref::<Assets>().unwrap().loaded == 10, "old box");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn container() {
+    use any_trait::container::{Container, ResolveError};
+    trait Clock {
+        fn now(&self) -> u64;
+    }
+    trait Storage {
+        fn stamp(&self) -> u64;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Clock)]
+    struct FixedClock {
+        at: u64,
+    }
+    impl Clock for FixedClock {
+        fn now(&self) -> u64 {
+            self.at
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Storage)]
+    struct Db {
+        created: u64,
+    }
+    impl Storage for Db {
+        fn stamp(&self) -> u64 {
+            self.created
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Storage)]
+    struct Memory {}
+    impl Storage for Memory {
+        fn stamp(&self) -> u64 {
+            0
+        }
+    }
+
+    let mut app = Container::new();
+    app.register(FixedClock { at: 10 });
+    app.register_factory(|c| Db {
+        created: c.resolve::<dyn Clock>().unwrap().now(),
+    });
+    assert!(app.contains::<dyn Storage>(), "contains");
+    assert!(
+        app.resolve::<dyn Storage>().unwrap().stamp() == 10,
+        "factory"
+    );
+    assert!(app.resolve::<Db>().is_ok(), "resolve concrete");
+
+    // the child overrides the clock, but `Db` was already built
+    let mut test = app.child();
+    test.register(FixedClock { at: 99 });
+    assert!(test.resolve::<dyn Clock>().unwrap().now() == 99, "override");
+    assert!(
+        test.resolve::<dyn Storage>().unwrap().stamp() == 10,
+        "parent"
+    );
+
+    // a factory in the child resolves its deps from the child
+    let mut scoped = test.child();
+    scoped.register_factory(|c| Db {
+        created: c.resolve::<dyn Clock>().unwrap().now(),
+    });
+    assert!(
+        scoped.resolve::<dyn Storage>().unwrap().stamp() == 99,
+        "scope"
+    );
+
+    trait Missing {}
+    match app.resolve::<dyn Missing>() {
+        Err(ResolveError::Missing { requested }) => {
+            assert!(requested.contains("Missing"), "requested: {}", requested)
+        }
+        _ => panic!("resolve of missing trait"),
+    }
+
+    app.register(Memory {});
+    match app.resolve::<dyn Storage>() {
+        Err(ResolveError::Ambiguous { candidates, .. }) => {
+            assert!(candidates.len() == 2, "candidates: {:?}", candidates);
+            assert!(candidates[1].ends_with("Memory"), "{:?}", candidates);
+        }
+        _ => panic!("ambiguous resolve"),
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn event_bus() {
+    use any_trait::eventbus::EventBus;
+    use std::{cell::Cell, rc::Rc};
+    trait OnResize {
+        fn on_resize(&self, w: u32);
+    }
+    trait OnKey {
+        fn on_key(&self, key: char);
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(OnResize, OnKey)]
+    struct Window {
+        width: Cell<u32>,
+        keys: Cell<usize>,
+    }
+    impl OnResize for Window {
+        fn on_resize(&self, w: u32) {
+            self.width.set(w);
+        }
+    }
+    impl OnKey for Window {
+        fn on_key(&self, _key: char) {
+            self.keys.set(self.keys.get() + 1);
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(OnKey)]
+    struct Logger {
+        keys: Cell<usize>,
+    }
+    impl OnKey for Logger {
+        fn on_key(&self, _key: char) {
+            self.keys.set(self.keys.get() + 1);
+        }
+    }
+
+    let mut bus = EventBus::new();
+    let win = Rc::new(Window {
+        width: Cell::new(0),
+        keys: Cell::new(0),
+    });
+    let log = Rc::new(Logger { keys: Cell::new(0) });
+    let win_any: Rc<dyn AnyTrait> = win.clone();
+    let log_sub = bus.subscribe(log.clone());
+    bus.subscribe_weak(&win_any);
+
+    assert!(bus.publish::<dyn OnResize, _>(|h| h.on_resize(10)) == 1);
+    assert!(bus.publish::<dyn OnKey, _>(|h| h.on_key('a')) == 2);
+    assert!(win.width.get() == 10, "resize not delivered");
+    assert!(
+        win.keys.get() == 1 && log.keys.get() == 1,
+        "key not delivered"
+    );
+    assert!(bus.count::<dyn OnKey>() == 2, "count");
+
+    // weak subscription goes away with the object
+    drop(win_any);
+    drop(win);
+    assert!(bus.count::<dyn OnKey>() == 1, "count after drop");
+    assert!(bus.publish::<dyn OnResize, _>(|h| h.on_resize(20)) == 0);
+    bus.prune();
+
+    assert!(bus.unsubscribe(log_sub), "unsubscribe");
+    assert!(!bus.unsubscribe(log_sub), "unsubscribe twice");
+    assert!(bus.publish::<dyn OnKey, _>(|h| h.on_key('b')) == 0);
+    assert!(log.keys.get() == 1, "delivered after unsubscribe");
+    assert!(Rc::strong_count(&log) == 1, "bus still holds the logger");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn event_bus_duplicate_listing() {
+    use any_trait::{
+        anyptr::AnyPtr, eventbus::EventBus, typeidconst::TypeIdConst,
+    };
+    use std::{cell::Cell, rc::Rc};
+    trait OnKey {
+        fn on_key(&self, key: char);
+    }
+    struct Keys {
+        count: Cell<usize>,
+    }
+    impl OnKey for Keys {
+        fn on_key(&self, _key: char) {
+            self.count.set(self.count.get() + 1);
+        }
+    }
+    // hand-written, lists `dyn OnKey` twice
+    impl AnyTrait for Keys {
+        fn type_ids(&self) -> &'static [TypeIdConst] {
+            const TRAITS: [TypeIdConst; 4] = [
+                TypeIdConst::of::<dyn AnyTrait>(),
+                TypeIdConst::of::<Keys>(),
+                TypeIdConst::of::<dyn OnKey>(),
+                TypeIdConst::of::<dyn OnKey>(),
+            ];
+            &TRAITS
+        }
+        fn type_erase(&self, trait_num: usize) -> AnyPtr {
+            match trait_num {
+                0 => AnyPtr::from::<dyn AnyTrait>(self as *const dyn AnyTrait),
+                1 => AnyPtr::from::<Keys>(self as *const Keys),
+                _ => AnyPtr::from::<dyn OnKey>(self as *const dyn OnKey),
+            }
+        }
+        fn type_erase_mut(&mut self, trait_num: usize) -> AnyPtr {
+            match trait_num {
+                0 => {
+                    AnyPtr::from_mut::<dyn AnyTrait>(self as *mut dyn AnyTrait)
+                }
+                1 => AnyPtr::from_mut::<Keys>(self as *mut Keys),
+                _ => AnyPtr::from_mut::<dyn OnKey>(self as *mut dyn OnKey),
+            }
+        }
+    }
+
+    let mut bus = EventBus::new();
+    let keys = Rc::new(Keys {
+        count: Cell::new(0),
+    });
+    let sub = bus.subscribe(keys.clone());
+    assert!(bus.count::<dyn OnKey>() == 1, "count");
+    assert!(bus.publish::<dyn OnKey, _>(|h| h.on_key('a')) == 1);
+    assert!(
+        keys.count.get() == 1,
+        "delivered {} times",
+        keys.count.get()
+    );
+    assert!(bus.unsubscribe(sub), "unsubscribe");
+    assert!(bus.count::<dyn OnKey>() == 0, "count after unsubscribe");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn cast_all() {
+    use any_trait::children::Walk;
+    trait Draw {
+        fn id(&self) -> usize;
+        fn set(&mut self, id: usize);
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Draw)]
+    struct Button {
+        id: usize,
+    }
+    impl Draw for Button {
+        fn id(&self) -> usize {
+            self.id
+        }
+        fn set(&mut self, id: usize) {
+            self.id = id;
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Draw)]
+    struct Panel {
+        id: usize,
+        #[any_children]
+        widgets: Vec<Box<dyn AnyTrait>>,
+        #[any_children]
+        fixed: [Button; 1],
+        #[any_children]
+        close: Option<Button>,
+    }
+    impl Draw for Panel {
+        fn id(&self) -> usize {
+            self.id
+        }
+        fn set(&mut self, id: usize) {
+            self.id = id;
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Label {}
+
+    let inner = Panel {
+        id: 10,
+        widgets: vec![Box::new(Button { id: 11 }), Box::new(Label {})],
+        fixed: [Button { id: 12 }],
+        close: None,
+    };
+    let mut root = Panel {
+        id: 0,
+        widgets: vec![Box::new(inner), Box::new(Button { id: 1 })],
+        fixed: [Button { id: 2 }],
+        close: Some(Button { id: 3 }),
+    };
+
+    let mut ids = Vec::new();
+    root.cast_all::<dyn Draw, _>(Walk::pre(), |d| ids.push(d.id()));
+    assert!(ids == [0, 10, 11, 12, 1, 2, 3], "pre-order: {:?}", ids);
+
+    ids.clear();
+    root.cast_all::<dyn Draw, _>(Walk::post(), |d| ids.push(d.id()));
+    assert!(ids == [11, 12, 10, 1, 2, 3, 0], "post-order: {:?}", ids);
+
+    ids.clear();
+    root.cast_all::<dyn Draw, _>(Walk::pre().max_depth(1), |d| {
+        ids.push(d.id())
+    });
+    assert!(ids == [0, 10, 1, 2, 3], "max_depth: {:?}", ids);
+
+    let mut labels = 0;
+    root.cast_all::<Label, _>(Walk::pre(), |_| labels += 1);
+    assert!(labels == 1, "concrete children: {}", labels);
+
+    root.cast_all_mut::<dyn Draw, _>(Walk::pre(), |d| d.set(d.id() + 100));
+    ids.clear();
+    root.cast_all::<dyn Draw, _>(Walk::pre(), |d| ids.push(d.id()));
+    assert!(
+        ids == [100, 110, 111, 112, 101, 102, 103],
+        "cast_all_mut: {:?}",
+        ids
+    );
+
+    #[derive(AnySubTrait)]
+    struct Shared {
+        #[any_children]
+        widgets: Vec<Box<dyn AnyTrait + Send + Sync>>,
+    }
+    let mut shared = Shared {
+        widgets: vec![Box::new(Button { id: 4 }), Box::new(Label {})],
+    };
+    shared.cast_all_mut::<dyn Draw, _>(Walk::pre(), |d| d.set(5));
+    ids.clear();
+    shared.cast_all::<dyn Draw, _>(Walk::pre(), |d| ids.push(d.id()));
+    assert!(ids == [5], "Send + Sync children: {:?}", ids);
+}
+
+#[test]
+fn compose() {
+    use any_trait::{AnyTraitStatic, compose::Compose};
+    trait Position {
+        fn x(&self) -> i32;
+        fn set_x(&mut self, x: i32);
+    }
+    trait Health {
+        fn hp(&self) -> u32;
+    }
+    trait Name {
+        fn name(&self) -> &'static str;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Position, Name)]
+    struct Body {
+        x: i32,
+    }
+    impl Position for Body {
+        fn x(&self) -> i32 {
+            self.x
+        }
+        fn set_x(&mut self, x: i32) {
+            self.x = x;
+        }
+    }
+    impl Name for Body {
+        fn name(&self) -> &'static str {
+            "body"
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Health, Name)]
+    struct Life {
+        hp: u32,
+    }
+    impl Health for Life {
+        fn hp(&self) -> u32 {
+            self.hp
+        }
+    }
+    impl Name for Life {
+        fn name(&self) -> &'static str {
+            "life"
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_send_sync]
+    struct Tag {}
+
+    let mut both = Compose(Body { x: 1 }, Life { hp: 100 });
+    // dyn AnyTrait, Compose, dyn Any, Body, Position, Name, Life, Health
+    assert!(both.type_ids().len() == 8, "{:?}", both.trait_names());
+    assert!(
+        both.type_name().contains("Compose"),
+        "type_name: {}",
+        both.type_name()
+    );
+    {
+        let any: &dyn AnyTrait = &both;
+        assert!(any.cast_ref::<dyn Position>().unwrap().x() == 1, "left");
+        assert!(any.cast_ref::<dyn Health>().unwrap().hp() == 100, "right");
+        assert!(any.cast_ref::<dyn Name>().unwrap().name() == "body", "bias");
+        assert!(any.cast_ref::<Life>().unwrap().hp == 100, "concrete B");
+        assert!(
+            any.cast_ref::<Compose<Body, Life>>().is_some(),
+            "concrete Compose"
+        );
+        let as_any = any.cast_ref::<dyn std::any::Any>().unwrap();
+        assert!(as_any.is::<Compose<Body, Life>>(), "dyn Any is the Compose");
+    }
+    both.cast_mut::<dyn Position>().unwrap().set_x(5);
+    assert!(both.0.x == 5, "cast_mut through Compose");
+    assert!(both.static_cast::<dyn Health>().hp() == 100, "static_cast");
+
+    // tuples and nesting
+    let nested = (Compose(Tag {}, Life { hp: 7 }), Body { x: 9 });
+    let any: &dyn AnyTrait = &nested;
+    assert!(any.cast_ref::<dyn Health>().unwrap().hp() == 7, "nested");
+    assert!(
+        any.cast_ref::<dyn Name>().unwrap().name() == "life",
+        "nested"
+    );
+    assert!(any.cast_ref::<Tag>().is_some(), "nested concrete");
+    assert!(
+        !any.implements::<dyn std::any::Any + Send + Sync>(),
+        "component dyn Any + Send + Sync listed"
+    );
+    assert!(any.cast_ref::<dyn Position>().unwrap().x() == 9, "tuple");
+    assert!(
+        any.cast_ref::<Compose<Tag, Life>>().is_some(),
+        "nested Compose"
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn adapter_registry() {
+    use any_trait::{
+        adapt::{Adapter, AdapterRegistry},
+        adapter,
+    };
+
+    trait OldLogger {
+        fn old_log(&self, msg: &str) -> usize;
+    }
+    trait Logger {
+        fn log(&self, msg: &str) -> usize;
+    }
+    struct LoggerShim<'a>(&'a dyn OldLogger);
+    impl<'a> From<&'a dyn OldLogger> for LoggerShim<'a> {
+        fn from(old: &'a dyn OldLogger) -> Self {
+            LoggerShim(old)
+        }
+    }
+    impl Logger for LoggerShim<'_> {
+        fn log(&self, msg: &str) -> usize {
+            self.0.old_log(msg) * 10
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(OldLogger)]
+    struct Legacy {
+        prefix: String,
+    }
+    impl OldLogger for Legacy {
+        fn old_log(&self, msg: &str) -> usize {
+            self.prefix.len() + msg.len()
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Logger)]
+    struct Modern {}
+    impl Logger for Modern {
+        fn log(&self, msg: &str) -> usize {
+            msg.len()
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Nothing {}
+
+    let mut adapters = AdapterRegistry::new();
+    let legacy = Legacy {
+        prefix: "> ".to_string(),
+    };
+    assert!(
+        adapters
+            .cast_ref_or_adapt::<dyn Logger, _>(&legacy)
+            .is_none(),
+        "no adapter yet"
+    );
+    let adapter: Adapter<dyn Logger> =
+        adapter!(dyn OldLogger => dyn Logger, LoggerShim);
+    adapters.register(adapter);
+
+    let objs: [&dyn AnyTrait; 3] = [&legacy, &Modern {}, &Nothing {}];
+    {
+        let logger = adapters.cast_ref_or_adapt::<dyn Logger, _>(objs[0]);
+        let logger = logger.expect("adapt");
+        assert!(!logger.is_native(), "shim");
+        assert!(logger.log("hi") == 40, "through the shim");
+    }
+    let logger = adapters.cast_ref_or_adapt::<dyn Logger, _>(objs[1]);
+    let logger = logger.expect("native");
+    assert!(logger.is_native(), "native");
+    assert!(logger.log("hi") == 2, "native impl wins");
+    assert!(
+        adapters
+            .cast_ref_or_adapt::<dyn Logger, _>(objs[2])
+            .is_none(),
+        "nothing to adapt from"
+    );
+    assert!(adapters.can_adapt::<dyn Logger, _>(objs[0]), "can_adapt");
+    assert!(!adapters.can_adapt::<dyn Logger, _>(objs[2]), "can't adapt");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn any_bridge() {
+    use any_trait::bridge::AnyBridge;
+    use std::any::Any;
+    trait Shape {
+        fn area(&self) -> u32;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(Shape)]
+    #[any_send_sync]
+    struct Square {
+        side: u32,
+    }
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.side * self.side
+        }
+    }
+    #[derive(AnySubTrait)]
+    struct Unknown {}
+
+    // AnyTrait -> Any
+    let mut sq = Square { side: 3 };
+    assert!(sq.implements::<dyn Any>(), "dyn Any listed");
+    assert!(
+        sq.implements::<dyn Any + Send + Sync>(),
+        "Send + Sync listed"
+    );
+    assert!(!Unknown {}.implements::<dyn Any + Send>(), "only dyn Any");
+    let any = sq.cast_ref::<dyn Any>().unwrap();
+    assert!(any.downcast_ref::<Square>().unwrap().side == 3, "downcast");
+    let any = sq.cast_ref::<dyn Any + Send + Sync>().unwrap();
+    assert!(any.is::<Square>(), "Send + Sync downcast");
+
+    // Any -> AnyTrait
+    let mut bridge = AnyBridge::new();
+    bridge.register::<Square>();
+    bridge.register::<Square>();
+    assert!(bridge.contains::<Square>(), "registered");
+    assert!(!bridge.contains::<Unknown>(), "not registered");
+
+    let any: &dyn Any = &sq;
+    let obj = bridge.recover(any).expect("recover");
+    assert!(obj.cast_ref::<dyn Shape>().unwrap().area() == 9, "recover");
+    assert!(bridge.recover(&Unknown {}).is_none(), "unknown type");
+
+    let any: &mut dyn Any = &mut sq;
+    let obj = bridge.recover_mut(any).expect("recover_mut");
+    obj.cast_mut::<Square>().unwrap().side = 4;
+    assert!(sq.area() == 16, "recover_mut");
+
+    let boxed: Box<dyn Any + Send> = Box::new(Square { side: 2 });
+    let obj = bridge.recover_box(boxed).expect("recover_box");
+    assert!(
+        obj.cast_ref::<dyn Shape>().unwrap().area() == 4,
+        "recover_box"
+    );
+    let boxed: Box<dyn Any> = Box::new(Unknown {});
+    let back = bridge.recover_box(boxed).err().expect("not registered");
+    assert!(back.is::<Unknown>(), "box given back");
+}
+
+#[test]
+#[cfg_attr(miri, ignore = "miri never reuses a vtable")]
+fn vtable_recover() {
+    use any_trait::vtable;
+    trait TA {
+        fn add_one(&self) -> usize;
+    }
+    trait TB {
+        fn add_two(&mut self) -> usize;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, TB)]
+    #[any_vtables]
+    struct C {
+        val: usize,
+    }
+    impl TA for C {
+        fn add_one(&self) -> usize {
+            self.val + 1
+        }
+    }
+    impl TB for C {
+        fn add_two(&mut self) -> usize {
+            self.val += 2;
+            self.val
+        }
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA)]
+    #[any_vtables]
+    struct D {
+        val: u8,
+    }
+    impl TA for D {
+        fn add_one(&self) -> usize {
+            self.val as usize * 100
+        }
+    }
+
+    let mut s = C { val: 42 };
+    {
+        let ta_ref: &dyn TA = &s;
+        #[allow(unsafe_code)]
+        let none = unsafe { any_trait::recover(ta_ref) };
+        assert!(none.is_none(), "not registered");
+    }
+    vtable::register::<C>();
+    vtable::register::<C>();
+    vtable::register::<D>();
+
+    let ta_ref: &dyn TA = &s;
+    // SAFETY: `C` and `D` have different `TA` impls, both registered
+    #[allow(unsafe_code)]
+    let any = unsafe { any_trait::recover::<dyn TA>(ta_ref) }.expect("recover");
+    assert!(any.cast_ref::<C>().unwrap().val == 42, "recover concrete");
+    assert!(any.cast_ref::<dyn TA>().unwrap().add_one() == 43, "TA");
+
+    let tb_mut: &mut dyn TB = &mut s;
+    #[allow(unsafe_code)]
+    let any = unsafe { any_trait::recover_mut::<dyn TB>(tb_mut) }
+        .expect("recover_mut");
+    assert!(any.cast_mut::<dyn TB>().unwrap().add_two() == 44, "TB");
+    assert!(s.val == 44, "recover_mut");
+
+    let d = D { val: 1 };
+    let ta_ref: &dyn TA = &d;
+    #[allow(unsafe_code)]
+    let any =
+        unsafe { any_trait::recover::<dyn TA>(ta_ref) }.expect("recover D");
+    assert!(any.cast_ref::<D>().is_some(), "recovered the right type");
+    assert!(any.cast_ref::<C>().is_none(), "not C");
+}
+
+#[test]
+fn native_upcast() {
+    trait TB: AnyTrait {
+        fn get(&self) -> usize;
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TB)]
+    struct C {
+        val: usize,
+    }
+    impl TB for C {
+        fn get(&self) -> usize {
+            self.val
+        }
+    }
+
+    let mut s = C { val: 1 };
+    {
+        let tb: &mut dyn TB = &mut s;
+        let any = tb.as_anytrait_mut();
+        any.cast_mut::<C>().unwrap().val = 2;
+        let any = any.as_anytrait_mut();
+        any.cast_mut::<C>().unwrap().val += 1;
+    }
+    assert!(s.val == 3, "as_anytrait_mut: {}", s.val);
+
+    let tb: &dyn TB = &s;
+    let any = tb.as_anytrait();
+    assert!(std::ptr::addr_eq(any, &s), "same object");
+    assert!(any.cast_ref::<dyn TB>().unwrap().get() == 3, "round trip");
+    let send: &(dyn TB + Send) = &s;
+    assert!(send.as_anytrait().cast_ref::<C>().is_some(), "drop Send");
+
+    // generic receivers, sized or not
+    fn up<T: AnyTrait + ?Sized>(t: &T) -> &dyn AnyTrait {
+        t.as_anytrait()
+    }
+    fn up_mut<T: AnyTrait + ?Sized>(t: &mut T) -> &mut dyn AnyTrait {
+        t.as_anytrait_mut()
+    }
+    up_mut(&mut s).cast_mut::<C>().unwrap().val = 4;
+    {
+        let tb: &mut dyn TB = &mut s;
+        up_mut(tb).cast_mut::<C>().unwrap().val += 1;
+    }
+    let tb: &dyn TB = &s;
+    assert!(up(tb).cast_ref::<C>().unwrap().val == 5, "generic upcast");
+    assert!(std::ptr::addr_eq(up(&s), &s), "generic same object");
+}
+
+#[test]
+// `TA` is listed twice on purpose
+#[allow(clippy::duplicated_attributes)]
+fn derive_rows() {
+    use any_trait::typeidconst::TypeIdConst;
+    use std::any::Any;
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    // `Any` listed by hand, and `TA` twice
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, Any, TA)]
+    #[any_send_sync]
+    struct C {
+        val: usize,
+    }
+    impl TA for C {
+        fn get(&self) -> usize {
+            self.val
+        }
+    }
+
+    let c = C { val: 5 };
+    let rows =
+        |t: TypeIdConst| c.type_ids().iter().filter(|r| **r == t).count();
+    assert!(
+        rows(TypeIdConst::of::<dyn Any>()) == 1,
+        "{:?}",
+        c.trait_names()
+    );
+    assert!(
+        rows(TypeIdConst::of::<dyn Any + Send + Sync>()) == 1,
+        "{:?}",
+        c.trait_names()
+    );
+    assert!(
+        rows(TypeIdConst::of::<dyn TA>()) == 2,
+        "{:?}",
+        c.trait_names()
+    );
+
+    // every row is erased by its index, duplicates included
+    for (idx, t) in c.type_ids().iter().enumerate() {
+        let erased = c.erase_ref(idx);
+        if *t == TypeIdConst::of::<dyn TA>() {
+            let ta = erased.downcast::<dyn TA>().expect("dyn TA row");
+            assert!(ta.get() == 5, "row {}", idx);
+        } else if *t == TypeIdConst::of::<dyn TA + Send + Sync>() {
+            let ta = erased.downcast::<dyn TA + Send + Sync>();
+            assert!(ta.expect("dyn TA + Send + Sync row").get() == 5);
+        } else if *t == TypeIdConst::of::<dyn Any>() {
+            let any = erased.downcast::<dyn Any>().expect("dyn Any row");
+            assert!(any.is::<C>(), "dyn Any is C");
+        }
+    }
+}
+
+#[test]
+fn send_sync() {
+    use any_trait::{AnyTraitSync, AsAnyTraitSync};
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    trait TB: AnyTrait {
+        fn set(&mut self, val: usize);
+    }
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA, TB)]
+    #[any_send_sync]
+    struct C {
+        val: usize,
+    }
+    impl TA for C {
+        fn get(&self) -> usize {
+            self.val
+        }
+    }
+    impl TB for C {
+        fn set(&mut self, val: usize) {
+            self.val = val;
+        }
+    }
+
+    let mut s = C { val: 1 };
+    {
+        let tb: &mut (dyn TB + Send + Sync) = &mut s;
+        let any = tb.as_anytrait_sync_mut();
+        any.cast_mut::<dyn TB + Send + Sync>().unwrap().set(2);
+    }
+    let any: &AnyTraitSync = s.as_anytrait_sync();
+    assert!(any.implements::<dyn TA + Send + Sync>(), "TA + Send + Sync");
+    assert!(any.cast_ref::<dyn TA>().is_some(), "plain TA");
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let ta = any.cast_ref::<dyn TA + Send + Sync>().unwrap();
+            assert!(ta.get() == 2, "shared with a thread");
+        });
+    });
+
+    #[cfg(feature = "alloc")]
+    {
+        let boxed: Box<AnyTraitSync> =
+            Box::new(C { val: 3 }).into_anytrait_sync();
+        let val = std::thread::spawn(move || {
+            boxed.cast_ref::<dyn TA + Send + Sync>().unwrap().get()
+        })
+        .join()
+        .unwrap();
+        assert!(val == 3, "moved to a thread");
+    }
+}
+
+#[test]
+fn send_only() {
+    use any_trait::{AnyTraitSend, AsAnyTraitSend};
+    use std::cell::Cell;
+    trait TA {
+        fn get(&self) -> usize;
+    }
+    // `Send` but not `Sync`
+    #[derive(AnySubTrait)]
+    #[any_sub_trait(TA)]
+    struct C {
+        val: Cell<usize>,
+    }
+    impl TA for C {
+        fn get(&self) -> usize {
+            self.val.get()
+        }
+    }
+
+    let mut s = C { val: Cell::new(1) };
+    s.as_anytrait_send_mut().cast_mut::<C>().unwrap().val.set(2);
+    let any: &AnyTraitSend = s.as_anytrait_send();
+    assert!(any.cast_ref::<dyn TA>().unwrap().get() == 2, "plain TA");
+
+    #[cfg(feature = "alloc")]
+    {
+        let boxed: Box<AnyTraitSend> =
+            Box::new(C { val: Cell::new(3) }).into_anytrait_send();
+        let val = std::thread::spawn(move || {
+            let c = boxed.cast_ref::<C>().unwrap();
+            c.val.set(c.val.get() + 1);
+            boxed.cast_ref::<dyn TA>().unwrap().get()
+        })
+        .join()
+        .unwrap();
+        assert!(val == 4, "moved to a thread: {}", val);
+    }
+}